@@ -1,7 +1,10 @@
 CREATE SCHEMA IF NOT EXISTS binance;
+CREATE SCHEMA IF NOT EXISTS bitget;
 
 CREATE TABLE binance.order_books (
     time TIMESTAMPTZ NOT NULL,
+    symbol TEXT NOT NULL,            -- Symbol
+    market TEXT NOT NULL DEFAULT 'usdm', -- "usdm", "coinm" or "spot"
     price_level TEXT NOT NULL,       -- Price level as a string (since it's a hashmap key)
     quantity FLOAT4 NOT NULL,        -- Quantity as a float
     side TEXT NOT NULL               -- "bid" or "ask"
@@ -10,9 +13,24 @@ CREATE TABLE binance.order_books (
 -- Convert this table into a hypertable
 SELECT create_hypertable('binance.order_books', 'time');
 
+CREATE TABLE binance.partial_depths (
+    time TIMESTAMPTZ NOT NULL,
+    symbol TEXT NOT NULL,            -- Symbol
+    market TEXT NOT NULL DEFAULT 'usdm', -- "usdm", "coinm" or "spot"
+    levels SMALLINT NOT NULL,        -- Depth of the stream: 5, 10 or 20
+    last_update_id BIGINT NOT NULL,  -- Order book update ID of the snapshot
+    price_level TEXT NOT NULL,       -- Price level as a string
+    quantity FLOAT4 NOT NULL,        -- Quantity as a float
+    side TEXT NOT NULL               -- "bid" or "ask"
+);
+
+-- Convert this table into a hypertable
+SELECT create_hypertable('binance.partial_depths', 'time');
+
 CREATE TABLE binance.liquidations (
     event_time TIMESTAMPTZ NOT NULL,  -- Directly store the event time as a timestamp
     symbol TEXT NOT NULL,             -- Symbol
+    market TEXT NOT NULL DEFAULT 'usdm', -- "usdm", "coinm" or "spot"
     side TEXT NOT NULL,               -- "BUY" or "SELL"
     order_type TEXT NOT NULL,         -- Order Type
     time_in_force TEXT NOT NULL,      -- Time in force
@@ -31,21 +49,183 @@ SELECT create_hypertable('binance.liquidations', 'event_time');
 CREATE TABLE binance.agg_trades (
     event_time TIMESTAMPTZ NOT NULL,  -- Directly store the event time as a timestamp
     symbol TEXT NOT NULL,             -- Symbol
+    market TEXT NOT NULL DEFAULT 'usdm', -- "usdm", "coinm" or "spot"
     aggregate_trade_id BIGINT NOT NULL, -- Aggregate trade ID
     price FLOAT4 NOT NULL,           -- Price
     quantity FLOAT4 NOT NULL,        -- Quantity
     first_trade_id BIGINT NOT NULL,  -- First trade ID
     last_trade_id BIGINT NOT NULL,   -- Last trade ID
     trade_time TIMESTAMPTZ NOT NULL, -- Trade time as a timestamp
-    buyer_is_market_maker BOOLEAN NOT NULL -- Is the buyer the market maker?
+    buyer_is_market_maker BOOLEAN NOT NULL, -- Is the buyer the market maker?
+    UNIQUE (market, symbol, aggregate_trade_id, trade_time)
+);
+
+-- Convert this table into a hypertable, partitioned by trade time which live
+-- and backfilled trades share, so duplicates can be suppressed
+SELECT create_hypertable('binance.agg_trades', 'trade_time');
+
+CREATE TABLE binance.mark_prices (
+    event_time TIMESTAMPTZ NOT NULL,      -- Directly store the event time as a timestamp
+    symbol TEXT NOT NULL,                 -- Symbol
+    market TEXT NOT NULL DEFAULT 'usdm', -- "usdm", "coinm" or "spot"
+    mark_price FLOAT4 NOT NULL,           -- Mark price
+    index_price FLOAT4 NOT NULL,          -- Index price
+    estimated_settle_price FLOAT4 NOT NULL, -- Estimated settle price
+    funding_rate FLOAT4,                  -- Funding rate, NULL for delivery contracts
+    next_funding_time TIMESTAMPTZ NOT NULL -- Next funding time as a timestamp
+);
+
+-- Convert this table into a hypertable
+SELECT create_hypertable('binance.mark_prices', 'event_time');
+
+CREATE TABLE binance.klines (
+    open_time TIMESTAMPTZ NOT NULL,        -- Kline start time
+    symbol TEXT NOT NULL,                  -- Symbol
+    market TEXT NOT NULL DEFAULT 'usdm', -- "usdm", "coinm" or "spot"
+    contract_type TEXT NOT NULL DEFAULT '', -- e.g. "PERPETUAL" for continuous klines, empty otherwise
+    kline_interval TEXT NOT NULL,          -- Interval, e.g. "1m"
+    close_time TIMESTAMPTZ NOT NULL,       -- Kline close time
+    open FLOAT4 NOT NULL,                  -- Open price
+    high FLOAT4 NOT NULL,                  -- High price
+    low FLOAT4 NOT NULL,                   -- Low price
+    close FLOAT4 NOT NULL,                 -- Close price
+    volume FLOAT4 NOT NULL,                -- Base asset volume
+    quote_volume FLOAT4 NOT NULL,          -- Quote asset volume
+    trade_count BIGINT NOT NULL,           -- Number of trades
+    taker_buy_volume FLOAT4 NOT NULL,      -- Taker buy base asset volume
+    taker_buy_quote_volume FLOAT4 NOT NULL, -- Taker buy quote asset volume
+    is_closed BOOLEAN NOT NULL,            -- Is the kline final?
+    event_time TIMESTAMPTZ NOT NULL,       -- Event time of the last update
+    PRIMARY KEY (market, symbol, contract_type, kline_interval, open_time)
+);
+
+-- Convert this table into a hypertable
+SELECT create_hypertable('binance.klines', 'open_time');
+
+CREATE TABLE binance.book_tickers (
+    event_time TIMESTAMPTZ NOT NULL,   -- Directly store the event time as a timestamp
+    symbol TEXT NOT NULL,              -- Symbol
+    market TEXT NOT NULL DEFAULT 'usdm', -- "usdm", "coinm" or "spot"
+    update_id BIGINT NOT NULL,         -- Order book update ID
+    best_bid_price FLOAT4 NOT NULL,    -- Best bid price
+    best_bid_quantity FLOAT4 NOT NULL, -- Best bid quantity
+    best_ask_price FLOAT4 NOT NULL,    -- Best ask price
+    best_ask_quantity FLOAT4 NOT NULL, -- Best ask quantity
+    transaction_time TIMESTAMPTZ NOT NULL -- Transaction time as a timestamp
+);
+
+-- Convert this table into a hypertable
+SELECT create_hypertable('binance.book_tickers', 'event_time');
+
+CREATE TABLE binance.open_interest (
+    time TIMESTAMPTZ NOT NULL,      -- Time the open interest was reported
+    symbol TEXT NOT NULL,           -- Symbol
+    market TEXT NOT NULL DEFAULT 'usdm', -- "usdm", "coinm" or "spot"
+    open_interest FLOAT4 NOT NULL,  -- Open interest in contracts
+    open_interest_value FLOAT4,     -- Notional value, only known for backfilled history
+    UNIQUE (market, symbol, time)
+);
+
+-- Convert this table into a hypertable
+SELECT create_hypertable('binance.open_interest', 'time');
+
+CREATE TABLE binance.long_short_ratios (
+    time TIMESTAMPTZ NOT NULL,        -- Start of the period
+    symbol TEXT NOT NULL,             -- Symbol
+    market TEXT NOT NULL DEFAULT 'usdm', -- "usdm", "coinm" or "spot"
+    ratio_type TEXT NOT NULL,         -- "global_account", "top_account" or "top_position"
+    period TEXT NOT NULL,             -- Period, e.g. "5m"
+    long_short_ratio FLOAT4 NOT NULL, -- Long/short ratio
+    long_share FLOAT4 NOT NULL,       -- Long share of accounts or positions
+    short_share FLOAT4 NOT NULL,      -- Short share of accounts or positions
+    UNIQUE (market, symbol, ratio_type, period, time)
+);
+
+-- Convert this table into a hypertable
+SELECT create_hypertable('binance.long_short_ratios', 'time');
+
+CREATE TABLE binance.taker_volumes (
+    time TIMESTAMPTZ NOT NULL,       -- Start of the period
+    symbol TEXT NOT NULL,            -- Symbol
+    market TEXT NOT NULL DEFAULT 'usdm', -- "usdm", "coinm" or "spot"
+    period TEXT NOT NULL,            -- Period, e.g. "5m"
+    buy_sell_ratio FLOAT4 NOT NULL,  -- Taker buy/sell volume ratio
+    buy_volume FLOAT4 NOT NULL,      -- Taker buy volume
+    sell_volume FLOAT4 NOT NULL,     -- Taker sell volume
+    UNIQUE (market, symbol, period, time)
+);
+
+-- Convert this table into a hypertable
+SELECT create_hypertable('binance.taker_volumes', 'time');
+
+CREATE TABLE bitget.order_books (
+    time TIMESTAMPTZ NOT NULL,
+    symbol TEXT NOT NULL,            -- Symbol
+    inst_type TEXT NOT NULL,         -- e.g. "USDT-FUTURES"
+    action TEXT NOT NULL,            -- "snapshot" (full book) or "update" (changed levels)
+    price_level TEXT NOT NULL,       -- Price level as a string
+    quantity FLOAT4 NOT NULL,        -- Quantity as a float, 0 removes the level
+    side TEXT NOT NULL               -- "bid" or "ask"
+);
+
+-- Convert this table into a hypertable
+SELECT create_hypertable('bitget.order_books', 'time');
+
+CREATE TABLE bitget.trades (
+    time TIMESTAMPTZ NOT NULL,       -- Trade time as a timestamp
+    symbol TEXT NOT NULL,            -- Symbol
+    inst_type TEXT NOT NULL,         -- e.g. "USDT-FUTURES"
+    trade_id BIGINT NOT NULL,        -- Trade ID
+    price FLOAT4 NOT NULL,           -- Price
+    size FLOAT4 NOT NULL,            -- Quantity
+    side TEXT NOT NULL,              -- Taker side, "buy" or "sell"
+    UNIQUE (inst_type, symbol, trade_id, time)
+);
+
+-- Convert this table into a hypertable; trades replayed after subscribing are dropped as duplicates
+SELECT create_hypertable('bitget.trades', 'time');
+
+CREATE TABLE bitget.tickers (
+    time TIMESTAMPTZ NOT NULL,         -- Ticker time as a timestamp
+    symbol TEXT NOT NULL,              -- Symbol
+    inst_type TEXT NOT NULL,           -- e.g. "USDT-FUTURES"
+    last_price FLOAT4 NOT NULL,        -- Last price
+    bid_price FLOAT4 NOT NULL,         -- Best bid price
+    ask_price FLOAT4 NOT NULL,         -- Best ask price
+    bid_size FLOAT4 NOT NULL,          -- Best bid size
+    ask_size FLOAT4 NOT NULL,          -- Best ask size
+    base_volume FLOAT4 NOT NULL,       -- 24h volume in base coin
+    quote_volume FLOAT4 NOT NULL,      -- 24h volume in quote coin
+    mark_price FLOAT4,                 -- Mark price, futures only
+    index_price FLOAT4,                -- Index price, futures only
+    funding_rate FLOAT4,               -- Current funding rate, futures only
+    next_funding_time TIMESTAMPTZ,     -- Next funding settlement, futures only
+    holding_amount FLOAT4              -- Open interest in base coin, futures only
+);
+
+-- Convert this table into a hypertable
+SELECT create_hypertable('bitget.tickers', 'time');
+
+CREATE TABLE bitget.checksum_failures (
+    time TIMESTAMPTZ NOT NULL,       -- Time of the update that failed the checksum
+    symbol TEXT NOT NULL,            -- Symbol
+    inst_type TEXT NOT NULL,         -- e.g. "USDT-FUTURES"
+    channel TEXT NOT NULL,           -- Depth channel, e.g. "books"
+    failures BIGINT NOT NULL         -- Mismatches of the book since the collector started
 );
 
 -- Convert this table into a hypertable
-SELECT create_hypertable('binance.agg_trades', 'event_time');
+SELECT create_hypertable('bitget.checksum_failures', 'time');
 
 SELECT add_retention_policy('binance.order_books', INTERVAL '3 days');
+SELECT add_retention_policy('binance.partial_depths', INTERVAL '3 days');
 SELECT add_retention_policy('binance.liquidations', INTERVAL '3 days');
 SELECT add_retention_policy('binance.agg_trades', INTERVAL '3 days');
+SELECT add_retention_policy('binance.mark_prices', INTERVAL '3 days');
+SELECT add_retention_policy('binance.book_tickers', INTERVAL '3 days');
+SELECT add_retention_policy('bitget.order_books', INTERVAL '3 days');
+SELECT add_retention_policy('bitget.trades', INTERVAL '3 days');
+SELECT add_retention_policy('bitget.tickers', INTERVAL '3 days');
 
 -- Create the table
 CREATE TABLE binance.strategy_features (
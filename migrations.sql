@@ -0,0 +1,100 @@
+-- Upgrades a database created from an earlier ddl.sql in place. First create
+-- the schemas and tables the database doesn't have yet with their statements
+-- from ddl.sql (including their retention policies), then run this file.
+-- Every statement is skipped when the database already has the change, so the
+-- whole file can be run against a database of any earlier version.
+
+-- Order book rows are keyed by symbol. Rows stored before keep an empty symbol
+ALTER TABLE binance.order_books ADD COLUMN IF NOT EXISTS symbol TEXT NOT NULL DEFAULT '';
+ALTER TABLE binance.order_books ALTER COLUMN symbol DROP DEFAULT;
+
+-- Delivery contracts send an empty funding rate, stored as NULL
+ALTER TABLE IF EXISTS binance.mark_prices ALTER COLUMN funding_rate DROP NOT NULL;
+
+-- Every Binance table gets a market column. Rows stored before are USD-M
+ALTER TABLE binance.order_books ADD COLUMN IF NOT EXISTS market TEXT NOT NULL DEFAULT 'usdm';
+ALTER TABLE binance.liquidations ADD COLUMN IF NOT EXISTS market TEXT NOT NULL DEFAULT 'usdm';
+ALTER TABLE binance.agg_trades ADD COLUMN IF NOT EXISTS market TEXT NOT NULL DEFAULT 'usdm';
+ALTER TABLE IF EXISTS binance.mark_prices ADD COLUMN IF NOT EXISTS market TEXT NOT NULL DEFAULT 'usdm';
+ALTER TABLE IF EXISTS binance.klines ADD COLUMN IF NOT EXISTS market TEXT NOT NULL DEFAULT 'usdm';
+ALTER TABLE IF EXISTS binance.book_tickers ADD COLUMN IF NOT EXISTS market TEXT NOT NULL DEFAULT 'usdm';
+ALTER TABLE IF EXISTS binance.open_interest ADD COLUMN IF NOT EXISTS market TEXT NOT NULL DEFAULT 'usdm';
+ALTER TABLE IF EXISTS binance.long_short_ratios ADD COLUMN IF NOT EXISTS market TEXT NOT NULL DEFAULT 'usdm';
+ALTER TABLE IF EXISTS binance.taker_volumes ADD COLUMN IF NOT EXISTS market TEXT NOT NULL DEFAULT 'usdm';
+
+-- Continuous klines are told apart from regular klines by their contract type.
+-- Rows stored before can't be told apart and keep an empty contract type
+ALTER TABLE IF EXISTS binance.klines ADD COLUMN IF NOT EXISTS contract_type TEXT NOT NULL DEFAULT '';
+
+-- Keys that upserts and duplicate suppression rely on include the market. Each
+-- key is only rebuilt when missing, as rebuilding revalidates the whole table
+DO $$
+BEGIN
+    IF to_regclass('binance.klines') IS NOT NULL AND NOT EXISTS (
+        SELECT 1 FROM pg_constraint
+        WHERE conrelid = to_regclass('binance.klines')
+            AND conname = 'klines_pkey'
+            AND pg_get_constraintdef(oid)
+                = 'PRIMARY KEY (market, symbol, contract_type, kline_interval, open_time)'
+    ) THEN
+        ALTER TABLE binance.klines
+            DROP CONSTRAINT IF EXISTS klines_pkey,
+            ADD PRIMARY KEY (market, symbol, contract_type, kline_interval, open_time);
+    END IF;
+
+    IF to_regclass('binance.open_interest') IS NOT NULL AND NOT EXISTS (
+        SELECT 1 FROM pg_constraint
+        WHERE conrelid = to_regclass('binance.open_interest')
+            AND conname = 'open_interest_market_symbol_time_key'
+    ) THEN
+        ALTER TABLE binance.open_interest
+            DROP CONSTRAINT IF EXISTS open_interest_symbol_time_key,
+            ADD CONSTRAINT open_interest_market_symbol_time_key UNIQUE (market, symbol, time);
+    END IF;
+
+    IF to_regclass('binance.long_short_ratios') IS NOT NULL AND NOT EXISTS (
+        SELECT 1 FROM pg_constraint
+        WHERE conrelid = to_regclass('binance.long_short_ratios')
+            AND conname = 'long_short_ratios_market_symbol_ratio_type_period_time_key'
+    ) THEN
+        ALTER TABLE binance.long_short_ratios
+            DROP CONSTRAINT IF EXISTS long_short_ratios_symbol_ratio_type_period_time_key,
+            ADD CONSTRAINT long_short_ratios_market_symbol_ratio_type_period_time_key
+                UNIQUE (market, symbol, ratio_type, period, time);
+    END IF;
+
+    IF to_regclass('binance.taker_volumes') IS NOT NULL AND NOT EXISTS (
+        SELECT 1 FROM pg_constraint
+        WHERE conrelid = to_regclass('binance.taker_volumes')
+            AND conname = 'taker_volumes_market_symbol_period_time_key'
+    ) THEN
+        ALTER TABLE binance.taker_volumes
+            DROP CONSTRAINT IF EXISTS taker_volumes_symbol_period_time_key,
+            ADD CONSTRAINT taker_volumes_market_symbol_period_time_key
+                UNIQUE (market, symbol, period, time);
+    END IF;
+END $$;
+
+-- Agg trades are partitioned by trade time and unique per trade. A hypertable
+-- can't change its time column, so the table is rebuilt with its rows
+DO $$
+BEGIN
+    IF EXISTS (
+        SELECT 1 FROM timescaledb_information.dimensions
+        WHERE hypertable_schema = 'binance'
+            AND hypertable_name = 'agg_trades'
+            AND column_name = 'event_time'
+    ) THEN
+        ALTER TABLE binance.agg_trades RENAME TO agg_trades_old;
+        CREATE TABLE binance.agg_trades (
+            LIKE binance.agg_trades_old INCLUDING DEFAULTS,
+            UNIQUE (market, symbol, aggregate_trade_id, trade_time)
+        );
+        PERFORM create_hypertable('binance.agg_trades', 'trade_time');
+        INSERT INTO binance.agg_trades
+            SELECT * FROM binance.agg_trades_old
+            ON CONFLICT DO NOTHING;
+        DROP TABLE binance.agg_trades_old;
+        PERFORM add_retention_policy('binance.agg_trades', INTERVAL '3 days');
+    END IF;
+END $$;
@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Exponential backoff with jitter for websocket reconnects.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    current: Duration,
+    attempt: u32,
+}
+
+#[allow(dead_code)]
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max,
+            current: initial,
+            attempt: 0,
+        }
+    }
+
+    /// Number of consecutive reconnect attempts since the last reset.
+    pub fn attempt(&self) -> u32 {
+        self.attempt
+    }
+
+    /// Returns the next delay and doubles the base delay up to `max`.
+    /// Half of the delay is fixed and half is random ("equal jitter") so that
+    /// many streams dropped at once don't reconnect in lockstep.
+    pub fn next_delay(&mut self) -> Duration {
+        let base = self.current.as_millis() as u64;
+        let half = base / 2;
+        let jitter = rand::thread_rng().gen_range(0..=half);
+
+        self.attempt += 1;
+        self.current = (self.current * 2).min(self.max);
+
+        Duration::from_millis(half + jitter)
+    }
+
+    pub fn reset(&mut self) {
+        self.current = self.initial;
+        self.attempt = 0;
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(1), Duration::from_secs(60))
+    }
+}
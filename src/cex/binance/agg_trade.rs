@@ -1,9 +1,9 @@
-use futures::{SinkExt, StreamExt};
-use log::{error, info};
+use log::error;
 use serde::Deserialize;
 use tokio::sync::mpsc;
-use tokio_tungstenite::tungstenite::Message;
 
+use super::agg_trade_backfill::AggTradeGaps;
+use super::market::BinanceMarket;
 use super::websocket::BinanceData;
 
 #[allow(dead_code)]
@@ -26,42 +26,24 @@ pub struct AggregateTradeEvent {
     pub l: u64,    // Last trade ID
     pub T: u64,    // Trade time
     pub m: bool,   // Is the buyer the market maker?
+    #[serde(skip)]
+    pub market: BinanceMarket,
 }
 
-pub async fn handle_agg_trade<R, S>(mut read: R, mut write: S, tx: mpsc::Sender<BinanceData>)
-where
-    R: StreamExt<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
-    S: SinkExt<Message> + Unpin,
-    S::Error: std::fmt::Display,
-{
-    while let Some(msg) = read.next().await {
-        match msg {
-            Ok(Message::Text(text)) => {
-                match serde_json::from_str::<BinanceWebsocketAggTrade>(&text) {
-                    Ok(event) => {
-                        if tx
-                            .send(BinanceData::AggTrade(event.data.clone()))
-                            .await
-                            .is_err()
-                        {
-                            error!("Failed to send agg trade event");
-                        }
-                    }
-                    Err(e) => error!("Failed to parse event: {} - Error: {}", text, e),
-                }
+pub async fn handle_agg_trade(
+    text: &str,
+    market: BinanceMarket,
+    gaps: &mut AggTradeGaps,
+    tx: &mpsc::Sender<BinanceData>,
+) {
+    match serde_json::from_str::<BinanceWebsocketAggTrade>(text) {
+        Ok(mut event) => {
+            event.data.market = market;
+            gaps.observe(&event.data, tx);
+            if tx.send(BinanceData::AggTrade(event.data)).await.is_err() {
+                error!("Failed to send agg trade event");
             }
-            Ok(Message::Ping(payload)) => {
-                if let Err(e) = write.send(Message::Pong(payload)).await {
-                    error!("Failed to send Pong: {}", e);
-                }
-            }
-            Ok(Message::Pong(_)) => info!("Received Pong"),
-            Ok(Message::Close(reason)) => {
-                info!("WebSocket closed: {:?}", reason);
-                break;
-            }
-            Err(e) => error!("Error reading message: {}", e),
-            _ => (),
         }
+        Err(e) => error!("Failed to parse event: {} - Error: {}", text, e),
     }
 }
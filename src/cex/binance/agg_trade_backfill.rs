@@ -0,0 +1,276 @@
+use std::collections::HashMap;
+
+use log::{error, info, warn};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio::time::sleep;
+
+use crate::cex::backoff::Backoff;
+
+use super::agg_trade::AggregateTradeEvent;
+use super::market::BinanceMarket;
+use super::rest::BinanceRestClient;
+use super::websocket::BinanceData;
+
+// Maximum page size of the aggTrades endpoint
+const AGG_TRADES_LIMIT: usize = 1000;
+
+// startTime and endTime of one request may be at most an hour apart
+const TIME_WINDOW_MS: u64 = 60 * 60 * 1000;
+
+// Attempts per page before a gap fill gives up
+const MAX_PAGE_ATTEMPTS: u32 = 5;
+
+/// Aggregate trade as served by the REST API, which has no event time nor symbol.
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize)]
+pub struct AggTradeResponse {
+    pub a: u64,    // Aggregate trade ID
+    pub p: String, // Price
+    pub q: String, // Quantity
+    pub f: u64,    // First trade ID
+    pub l: u64,    // Last trade ID
+    pub T: u64,    // Trade time
+    pub m: bool,   // Is the buyer the market maker?
+}
+
+impl AggTradeResponse {
+    /// Backfilled trades use the trade time as event time.
+    fn into_event(self, symbol: &str, market: BinanceMarket) -> AggregateTradeEvent {
+        AggregateTradeEvent {
+            e: "aggTrade".to_string(),
+            E: self.T,
+            s: symbol.to_string(),
+            a: self.a,
+            p: self.p,
+            q: self.q,
+            f: self.f,
+            l: self.l,
+            T: self.T,
+            m: self.m,
+            market,
+        }
+    }
+}
+
+/// Trades to backfill, bounds inclusive.
+#[derive(Debug, Clone, Copy)]
+pub enum AggTradeRange {
+    Ids { from: u64, to: u64 },
+    Time { start: u64, end: u64 }, // Unix time in milliseconds
+}
+
+/// Pages through the aggTrades endpoint: locates the first trade of a time
+/// range hour by hour, then follows `fromId` until the end of the range.
+pub struct AggTradePager {
+    symbol: String, // Uppercase symbol
+    market: BinanceMarket,
+    range: AggTradeRange,
+    next_id: Option<u64>,
+    window_start: u64,
+    done: bool,
+    background: bool,
+}
+
+impl AggTradePager {
+    pub fn new(symbol: &str, market: BinanceMarket, range: AggTradeRange) -> Self {
+        let (next_id, window_start) = match range {
+            AggTradeRange::Ids { from, .. } => (Some(from), 0),
+            AggTradeRange::Time { start, .. } => (None, start),
+        };
+        Self {
+            symbol: symbol.to_uppercase(),
+            market,
+            range,
+            next_id,
+            window_start,
+            done: false,
+            background: false,
+        }
+    }
+
+    /// Spends only the part of the REST budget that depth snapshots and
+    /// pollers don't need, e.g. for gap fills next to live streams.
+    pub fn in_background(mut self) -> Self {
+        self.background = true;
+        self
+    }
+
+    /// Returns the next page of trades, which may be empty while an idle hour
+    /// is skipped, or `None` once the range is exhausted.
+    pub async fn next_page(&mut self) -> Result<Option<Vec<AggregateTradeEvent>>, reqwest::Error> {
+        if self.done {
+            return Ok(None);
+        }
+
+        let by_id = self.next_id.is_some();
+        let query = match (self.next_id, self.range) {
+            (Some(from_id), _) => format!("fromId={}", from_id),
+            (None, AggTradeRange::Time { end, .. }) => format!(
+                "startTime={}&endTime={}",
+                self.window_start,
+                (self.window_start + TIME_WINDOW_MS - 1).min(end)
+            ),
+            (None, AggTradeRange::Ids { from, .. }) => format!("fromId={}", from),
+        };
+        let trades = fetch_agg_trades(
+            BinanceRestClient::shared(),
+            &self.symbol,
+            self.market,
+            &query,
+            self.background,
+        )
+        .await?;
+
+        if !by_id && trades.is_empty() {
+            // No trade in this window, move on to the next hour
+            if let AggTradeRange::Time { end, .. } = self.range {
+                self.window_start += TIME_WINDOW_MS;
+                self.done = self.window_start > end;
+            }
+            return Ok(Some(Vec::new()));
+        }
+
+        // A short page by ID means the latest trade was reached
+        let exhausted = by_id && trades.len() < AGG_TRADES_LIMIT;
+        let mut events = Vec::with_capacity(trades.len());
+        for trade in trades {
+            let in_range = match self.range {
+                AggTradeRange::Ids { to, .. } => trade.a <= to,
+                AggTradeRange::Time { end, .. } => trade.T <= end,
+            };
+            if !in_range {
+                self.done = true;
+                break;
+            }
+            self.next_id = Some(trade.a + 1);
+            events.push(trade.into_event(&self.symbol, self.market));
+        }
+
+        if exhausted {
+            self.done = true;
+        }
+        Ok(Some(events))
+    }
+}
+
+pub async fn fetch_agg_trades(
+    client: &BinanceRestClient,
+    symbol: &str,
+    market: BinanceMarket,
+    query: &str,
+    background: bool,
+) -> Result<Vec<AggTradeResponse>, reqwest::Error> {
+    let url = format!(
+        "{}{}/aggTrades?symbol={}&{}&limit={}",
+        market.rest_base(),
+        market.api_prefix(),
+        symbol.to_uppercase(),
+        query,
+        AGG_TRADES_LIMIT
+    );
+
+    let weight = if market.is_futures() { 20 } else { 4 };
+    if background {
+        client
+            .get_json_background::<Vec<AggTradeResponse>>(market, &url, weight)
+            .await
+    } else {
+        client
+            .get_json::<Vec<AggTradeResponse>>(market, &url, weight)
+            .await
+    }
+}
+
+/// Tracks the last aggregate trade ID of every symbol on a connection.
+/// Aggregate trade IDs are consecutive, so when a live trade skips IDs, e.g.
+/// after a reconnect, the missing trades are fetched over REST in the
+/// background and sent behind the live ones.
+pub struct AggTradeGaps {
+    market: BinanceMarket,
+    last_ids: HashMap<String, u64>,
+}
+
+impl AggTradeGaps {
+    pub fn new(market: BinanceMarket) -> Self {
+        Self {
+            market,
+            last_ids: HashMap::new(),
+        }
+    }
+
+    /// Starts from the last stored ID of each symbol (uppercase symbol -> ID),
+    /// so the trades missed while the collector was down are backfilled too.
+    pub fn resume_from(&mut self, last_ids: &HashMap<String, u64>) {
+        for (symbol, last_id) in last_ids {
+            let current = self.last_ids.entry(symbol.clone()).or_insert(*last_id);
+            *current = (*current).max(*last_id);
+        }
+    }
+
+    pub fn observe(&mut self, event: &AggregateTradeEvent, tx: &mpsc::Sender<BinanceData>) {
+        match self.last_ids.get_mut(&event.s) {
+            Some(last_id) if event.a > *last_id + 1 => {
+                let range = AggTradeRange::Ids {
+                    from: *last_id + 1,
+                    to: event.a - 1,
+                };
+                *last_id = event.a;
+                fill_gap(event.s.clone(), self.market, range, tx.clone());
+            }
+            // Trades replayed while a connection rotates may arrive out of order
+            Some(last_id) => *last_id = (*last_id).max(event.a),
+            None => {
+                self.last_ids.insert(event.s.clone(), event.a);
+            }
+        }
+    }
+}
+
+/// Spawns a task that backfills `range` into `tx`.
+fn fill_gap(
+    symbol: String,
+    market: BinanceMarket,
+    range: AggTradeRange,
+    tx: mpsc::Sender<BinanceData>,
+) {
+    if let AggTradeRange::Ids { from, to } = range {
+        warn!(
+            "{} agg trades {}..={} were missed, backfilling {} trades",
+            symbol,
+            from,
+            to,
+            to - from + 1
+        );
+    }
+
+    tokio::spawn(async move {
+        let mut pager = AggTradePager::new(&symbol, market, range).in_background();
+        let mut backoff = Backoff::default();
+        let mut filled = 0;
+
+        loop {
+            match pager.next_page().await {
+                Ok(Some(events)) => {
+                    backoff.reset();
+                    filled += events.len();
+                    for event in events {
+                        if tx.send(BinanceData::AggTrade(event)).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    error!("Failed to backfill agg trades for {}: {}", symbol, e);
+                    if backoff.attempt() + 1 >= MAX_PAGE_ATTEMPTS {
+                        error!("Giving up agg trade backfill for {}", symbol);
+                        return;
+                    }
+                    sleep(backoff.next_delay()).await;
+                }
+            }
+        }
+        info!("Backfilled {} agg trades for {}", filled, symbol);
+    });
+}
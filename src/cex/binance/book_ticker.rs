@@ -0,0 +1,84 @@
+use log::error;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use super::market::BinanceMarket;
+use super::websocket::BinanceData;
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct BinanceWebsocketBookTicker {
+    pub stream: String,
+    pub data: BookTickerEvent,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct BinanceWebsocketSpotBookTicker {
+    pub stream: String,
+    pub data: SpotBookTickerEvent,
+}
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct BookTickerEvent {
+    pub e: String, // Event type
+    pub u: u64,    // Order book update ID
+    pub E: u64,    // Event time
+    pub T: u64,    // Transaction time
+    pub s: String, // Symbol
+    pub b: String, // Best bid price
+    pub B: String, // Best bid quantity
+    pub a: String, // Best ask price
+    pub A: String, // Best ask quantity
+    #[serde(skip)]
+    pub market: BinanceMarket,
+}
+
+/// Spot book tickers carry no event or transaction time.
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct SpotBookTickerEvent {
+    pub u: u64,    // Order book update ID
+    pub s: String, // Symbol
+    pub b: String, // Best bid price
+    pub B: String, // Best bid quantity
+    pub a: String, // Best ask price
+    pub A: String, // Best ask quantity
+}
+
+pub async fn handle_book_ticker(text: &str, market: BinanceMarket, tx: &mpsc::Sender<BinanceData>) {
+    let event = match market {
+        BinanceMarket::Spot => {
+            serde_json::from_str::<BinanceWebsocketSpotBookTicker>(text).map(|event| {
+                // Stamped with the local receive time instead
+                let now = chrono::Utc::now().timestamp_millis() as u64;
+                BookTickerEvent {
+                    e: "bookTicker".to_string(),
+                    u: event.data.u,
+                    E: now,
+                    T: now,
+                    s: event.data.s,
+                    b: event.data.b,
+                    B: event.data.B,
+                    a: event.data.a,
+                    A: event.data.A,
+                    market,
+                }
+            })
+        }
+        _ => serde_json::from_str::<BinanceWebsocketBookTicker>(text).map(|mut event| {
+            event.data.market = market;
+            event.data
+        }),
+    };
+
+    match event {
+        Ok(event) => {
+            if tx.send(BinanceData::BookTicker(event)).await.is_err() {
+                error!("Failed to send book ticker event");
+            }
+        }
+        Err(e) => error!("Failed to parse event: {} - Error: {}", text, e),
+    }
+}
@@ -0,0 +1,87 @@
+use log::error;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use super::market::BinanceMarket;
+use super::websocket::BinanceData;
+
+pub const CONTRACT_INFO_STREAM: &str = "!contractInfo";
+
+// Contract statuses after which a symbol no longer trades
+pub const CLOSED_CONTRACT_STATUSES: [&str; 2] = ["SETTLING", "CLOSE"];
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct BinanceWebsocketContractInfo {
+    pub stream: String,
+    pub data: ContractInfoEvent,
+}
+
+/// Pushed on listings, settlements and leverage bracket changes.
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct ContractInfoEvent {
+    pub e: String,  // Event type
+    pub E: u64,     // Event time
+    pub s: String,  // Symbol
+    pub ps: String, // Pair
+    pub ct: String, // Contract type, e.g. "PERPETUAL"
+    pub dt: u64,    // Delivery date time
+    pub ot: u64,    // Onboard date time
+    pub cs: String, // Contract status, e.g. "TRADING" or "SETTLING"
+    #[serde(default)]
+    pub bks: Vec<LeverageBracket>, // Only sent when brackets change
+    #[serde(skip)]
+    pub market: BinanceMarket,
+}
+
+#[allow(dead_code)]
+impl ContractInfoEvent {
+    pub fn is_closed(&self) -> bool {
+        CLOSED_CONTRACT_STATUSES.contains(&self.cs.as_str())
+    }
+
+    pub fn is_trading_perpetual(&self) -> bool {
+        self.cs == "TRADING" && self.ct == "PERPETUAL"
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct LeverageBracket {
+    pub bs: u32,  // Notional bracket
+    pub bnf: f64, // Floor notional of this bracket
+    pub bnc: f64, // Cap notional of this bracket
+    pub mmr: f64, // Maintenance ratio for this bracket
+    pub cf: f64,  // Auxiliary number for quick calculation
+    pub mi: u32,  // Min leverage for this bracket
+    pub ma: u32,  // Max leverage for this bracket
+}
+
+/// Forwards contract info to `tx`, and to `lifecycle_tx` so streams of
+/// settled symbols can be closed and new listings subscribed.
+pub async fn handle_contract_info(
+    text: &str,
+    market: BinanceMarket,
+    lifecycle_tx: Option<&mpsc::Sender<ContractInfoEvent>>,
+    tx: &mpsc::Sender<BinanceData>,
+) {
+    match serde_json::from_str::<BinanceWebsocketContractInfo>(text) {
+        Ok(mut event) => {
+            event.data.market = market;
+            if let Some(lifecycle_tx) = lifecycle_tx {
+                if lifecycle_tx.send(event.data.clone()).await.is_err() {
+                    error!("Failed to send contract info to the lifecycle manager");
+                }
+            }
+            if tx
+                .send(BinanceData::ContractInfo(event.data))
+                .await
+                .is_err()
+            {
+                error!("Failed to send contract info event");
+            }
+        }
+        Err(e) => error!("Failed to parse event: {} - Error: {}", text, e),
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+use super::market::BinanceMarket;
+use super::rest::BinanceRestClient;
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize)]
+pub struct ExchangeInfoResponse {
+    pub symbols: Vec<SymbolInfo>,
+}
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize)]
+pub struct SymbolInfo {
+    pub symbol: String,
+    #[serde(alias = "contractStatus")]
+    pub status: String, // "TRADING", "SETTLING", ... ("contractStatus" on COIN-M)
+    pub baseAsset: String,
+    pub quoteAsset: String,
+    pub contractType: Option<String>, // Futures only, e.g. "PERPETUAL"
+    pub onboardDate: Option<u64>,     // Futures only
+    pub filters: Vec<SymbolFilter>,
+}
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize)]
+#[serde(tag = "filterType")]
+pub enum SymbolFilter {
+    #[serde(rename = "PRICE_FILTER")]
+    Price { tickSize: String },
+    #[serde(rename = "LOT_SIZE")]
+    LotSize { stepSize: String },
+    #[serde(other)]
+    Other,
+}
+
+/// Trading rules of a symbol as listed by `exchangeInfo`.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct Instrument {
+    pub symbol: String, // Uppercase symbol
+    pub market: BinanceMarket,
+    pub status: String,
+    pub base_asset: String,
+    pub quote_asset: String,
+    pub contract_type: Option<String>,
+    pub onboard_date: Option<u64>, // Unix time in milliseconds
+    pub tick_size: String,
+    pub step_size: String,
+}
+
+#[allow(dead_code)]
+impl Instrument {
+    fn from_symbol_info(info: SymbolInfo, market: BinanceMarket) -> Self {
+        let mut tick_size = String::new();
+        let mut step_size = String::new();
+        for filter in info.filters {
+            match filter {
+                SymbolFilter::Price { tickSize } => tick_size = tickSize,
+                SymbolFilter::LotSize { stepSize } => step_size = stepSize,
+                SymbolFilter::Other => {}
+            }
+        }
+
+        Self {
+            symbol: info.symbol,
+            market,
+            status: info.status,
+            base_asset: info.baseAsset,
+            quote_asset: info.quoteAsset,
+            contract_type: info.contractType,
+            onboard_date: info.onboardDate,
+            tick_size,
+            step_size,
+        }
+    }
+
+    pub fn is_trading(&self) -> bool {
+        self.status == "TRADING"
+    }
+}
+
+/// Instruments of a market as listed by `exchangeInfo`, keyed by uppercase symbol.
+#[allow(dead_code)]
+#[derive(Debug, Clone, Default)]
+pub struct InstrumentRegistry {
+    market: BinanceMarket,
+    instruments: HashMap<String, Instrument>,
+}
+
+#[allow(dead_code)]
+impl InstrumentRegistry {
+    pub async fn load(market: BinanceMarket) -> Result<Self, Box<dyn std::error::Error>> {
+        let response = fetch_exchange_info(market).await?;
+        let instruments = response
+            .symbols
+            .into_iter()
+            .map(|info| {
+                let instrument = Instrument::from_symbol_info(info, market);
+                (instrument.symbol.clone(), instrument)
+            })
+            .collect();
+        Ok(Self {
+            market,
+            instruments,
+        })
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&Instrument> {
+        self.instruments.get(&symbol.to_uppercase())
+    }
+
+    /// Checks that every symbol is listed and currently trading.
+    pub fn validate<S: AsRef<str>>(&self, symbols: &[S]) -> Result<(), String> {
+        let mut problems = Vec::new();
+        for symbol in symbols {
+            let symbol = symbol.as_ref();
+            match self.get(symbol) {
+                None => problems.push(format!("{} is not listed", symbol)),
+                Some(instrument) if !instrument.is_trading() => {
+                    problems.push(format!("{} is {}", symbol, instrument.status))
+                }
+                Some(_) => {}
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(format!(
+                "Invalid Binance {} symbols: {}",
+                self.market,
+                problems.join(", ")
+            ))
+        }
+    }
+}
+
+pub async fn fetch_exchange_info(
+    market: BinanceMarket,
+) -> Result<ExchangeInfoResponse, reqwest::Error> {
+    let url = format!("{}{}/exchangeInfo", market.rest_base(), market.api_prefix());
+
+    let weight = if market.is_futures() { 1 } else { 20 };
+    BinanceRestClient::shared()
+        .get_json::<ExchangeInfoResponse>(market, &url, weight)
+        .await
+}
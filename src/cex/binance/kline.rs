@@ -0,0 +1,105 @@
+use log::error;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use super::market::BinanceMarket;
+use super::websocket::BinanceData;
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct BinanceWebsocketKline {
+    pub stream: String,
+    pub data: KlineEvent,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct BinanceWebsocketContinuousKline {
+    pub stream: String,
+    pub data: ContinuousKlineEvent,
+}
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct KlineEvent {
+    pub e: String, // Event type
+    pub E: u64,    // Event time
+    pub s: String, // Symbol
+    pub k: Kline,
+    #[serde(skip)]
+    pub market: BinanceMarket,
+    #[serde(skip)]
+    pub contract_type: String, // e.g. "PERPETUAL" for continuous klines, empty otherwise
+}
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct ContinuousKlineEvent {
+    pub e: String,  // Event type
+    pub E: u64,     // Event time
+    pub ps: String, // Pair
+    pub ct: String, // Contract type
+    pub k: Kline,
+}
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct Kline {
+    pub t: u64,    // Kline start time
+    pub T: u64,    // Kline close time
+    pub i: String, // Interval
+    pub f: i64,    // First trade ID
+    pub L: i64,    // Last trade ID
+    pub o: String, // Open price
+    pub c: String, // Close price
+    pub h: String, // High price
+    pub l: String, // Low price
+    pub v: String, // Base asset volume
+    pub n: u64,    // Number of trades
+    pub x: bool,   // Is this kline closed?
+    pub q: String, // Quote asset volume
+    pub V: String, // Taker buy base asset volume
+    pub Q: String, // Taker buy quote asset volume
+}
+
+// Intervals accepted by `<symbol>@kline_<interval>` and `continuousKline`
+pub const KLINE_INTERVALS: [&str; 15] = [
+    "1m", "3m", "5m", "15m", "30m", "1h", "2h", "4h", "6h", "8h", "12h", "1d", "3d", "1w", "1M",
+];
+
+pub async fn handle_kline(text: &str, market: BinanceMarket, tx: &mpsc::Sender<BinanceData>) {
+    match serde_json::from_str::<BinanceWebsocketKline>(text) {
+        Ok(mut event) => {
+            event.data.market = market;
+            if tx.send(BinanceData::Kline(event.data)).await.is_err() {
+                error!("Failed to send kline event");
+            }
+        }
+        Err(e) => error!("Failed to parse event: {} - Error: {}", text, e),
+    }
+}
+
+/// Continuous klines are stored under the pair, told apart from the regular
+/// klines of the same symbol by their contract type.
+pub async fn handle_continuous_kline(
+    text: &str,
+    market: BinanceMarket,
+    tx: &mpsc::Sender<BinanceData>,
+) {
+    match serde_json::from_str::<BinanceWebsocketContinuousKline>(text) {
+        Ok(event) => {
+            let kline = KlineEvent {
+                e: event.data.e,
+                E: event.data.E,
+                s: event.data.ps,
+                k: event.data.k,
+                market,
+                contract_type: event.data.ct,
+            };
+            if tx.send(BinanceData::Kline(kline)).await.is_err() {
+                error!("Failed to send kline event");
+            }
+        }
+        Err(e) => error!("Failed to parse event: {} - Error: {}", text, e),
+    }
+}
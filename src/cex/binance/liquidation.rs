@@ -1,9 +1,8 @@
-use futures::{SinkExt, StreamExt};
-use log::{error, info};
+use log::error;
 use serde::Deserialize;
 use tokio::sync::mpsc;
-use tokio_tungstenite::tungstenite::Message;
 
+use super::market::BinanceMarket;
 use super::websocket::BinanceData;
 
 #[allow(dead_code)]
@@ -19,6 +18,8 @@ pub struct LiquidationEvent {
     pub e: String, // Event type
     pub E: u64,    // Event time
     pub o: LiquidationOrder,
+    #[serde(skip)]
+    pub market: BinanceMarket,
 }
 
 #[allow(dead_code, non_snake_case)]
@@ -37,43 +38,18 @@ pub struct LiquidationOrder {
     pub T: u64,     // Transaction Time
 }
 
-pub async fn handle_liquidation_order<R, S>(
-    mut read: R,
-    mut write: S,
-    tx: mpsc::Sender<BinanceData>,
-) where
-    R: StreamExt<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
-    S: SinkExt<Message> + Unpin,
-    S::Error: std::fmt::Display,
-{
-    while let Some(msg) = read.next().await {
-        match msg {
-            Ok(Message::Text(text)) => {
-                match serde_json::from_str::<BinanceWebsocketLiquidation>(&text) {
-                    Ok(event) => {
-                        if tx
-                            .send(BinanceData::Liquidation(event.data.clone()))
-                            .await
-                            .is_err()
-                        {
-                            error!("Failed to send liquidation event");
-                        }
-                    }
-                    Err(e) => error!("Failed to parse event: {} - Error: {}", text, e),
-                }
+pub async fn handle_liquidation_order(
+    text: &str,
+    market: BinanceMarket,
+    tx: &mpsc::Sender<BinanceData>,
+) {
+    match serde_json::from_str::<BinanceWebsocketLiquidation>(text) {
+        Ok(mut event) => {
+            event.data.market = market;
+            if tx.send(BinanceData::Liquidation(event.data)).await.is_err() {
+                error!("Failed to send liquidation event");
             }
-            Ok(Message::Ping(payload)) => {
-                if let Err(e) = write.send(Message::Pong(payload)).await {
-                    error!("Failed to send Pong: {}", e);
-                }
-            }
-            Ok(Message::Pong(_)) => info!("Received Pong"),
-            Ok(Message::Close(reason)) => {
-                info!("WebSocket closed: {:?}", reason);
-                break;
-            }
-            Err(e) => error!("Error reading message: {}", e),
-            _ => (),
         }
+        Err(e) => error!("Failed to parse event: {} - Error: {}", text, e),
     }
 }
@@ -0,0 +1,40 @@
+use log::error;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use super::market::BinanceMarket;
+use super::websocket::BinanceData;
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct BinanceWebsocketMarkPrice {
+    pub stream: String,
+    pub data: MarkPriceEvent,
+}
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct MarkPriceEvent {
+    pub e: String, // Event type
+    pub E: u64,    // Event time
+    pub s: String, // Symbol
+    pub p: String, // Mark price
+    pub i: String, // Index price
+    pub P: String, // Estimated settle price, only useful in the last hour before settlement
+    pub r: String, // Funding rate, empty for delivery contracts
+    pub T: u64,    // Next funding time
+    #[serde(skip)]
+    pub market: BinanceMarket,
+}
+
+pub async fn handle_mark_price(text: &str, market: BinanceMarket, tx: &mpsc::Sender<BinanceData>) {
+    match serde_json::from_str::<BinanceWebsocketMarkPrice>(text) {
+        Ok(mut event) => {
+            event.data.market = market;
+            if tx.send(BinanceData::MarkPrice(event.data)).await.is_err() {
+                error!("Failed to send mark price event");
+            }
+        }
+        Err(e) => error!("Failed to parse event: {} - Error: {}", text, e),
+    }
+}
@@ -0,0 +1,85 @@
+use std::fmt;
+
+/// Binance market a stream or REST request is served from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum BinanceMarket {
+    #[default]
+    UsdM, // USDⓈ-M futures
+    CoinM, // COIN-M futures
+    Spot,
+}
+
+#[allow(dead_code)]
+impl BinanceMarket {
+    pub fn ws_base(&self) -> &'static str {
+        match self {
+            BinanceMarket::UsdM => "wss://fstream.binance.com",
+            BinanceMarket::CoinM => "wss://dstream.binance.com",
+            BinanceMarket::Spot => "wss://stream.binance.com:9443",
+        }
+    }
+
+    pub fn rest_base(&self) -> &'static str {
+        match self {
+            BinanceMarket::UsdM => "https://fapi.binance.com",
+            BinanceMarket::CoinM => "https://dapi.binance.com",
+            BinanceMarket::Spot => "https://api.binance.com",
+        }
+    }
+
+    /// Prefix of the market's REST API, e.g. "/fapi/v1".
+    pub fn api_prefix(&self) -> &'static str {
+        match self {
+            BinanceMarket::UsdM => "/fapi/v1",
+            BinanceMarket::CoinM => "/dapi/v1",
+            BinanceMarket::Spot => "/api/v3",
+        }
+    }
+
+    pub fn is_futures(&self) -> bool {
+        !matches!(self, BinanceMarket::Spot)
+    }
+
+    pub fn max_streams_per_connection(&self) -> usize {
+        match self {
+            BinanceMarket::UsdM | BinanceMarket::CoinM => 200,
+            BinanceMarket::Spot => 1024,
+        }
+    }
+
+    /// Request weight allowed per minute and IP on the REST API.
+    pub fn weight_limit_per_minute(&self) -> u32 {
+        match self {
+            BinanceMarket::UsdM | BinanceMarket::CoinM => 2400,
+            BinanceMarket::Spot => 6000,
+        }
+    }
+
+    /// Value stored in the `market` column.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BinanceMarket::UsdM => "usdm",
+            BinanceMarket::CoinM => "coinm",
+            BinanceMarket::Spot => "spot",
+        }
+    }
+}
+
+impl fmt::Display for BinanceMarket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for BinanceMarket {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "usdm" => Ok(BinanceMarket::UsdM),
+            "coinm" => Ok(BinanceMarket::CoinM),
+            "spot" => Ok(BinanceMarket::Spot),
+            _ => Err(format!("Unknown Binance market {}", s)),
+        }
+    }
+}
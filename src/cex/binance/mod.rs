@@ -1,4 +1,16 @@
 pub mod agg_trade;
+pub mod agg_trade_backfill;
+pub mod book_ticker;
+pub mod contract_info;
+pub mod exchange_info;
+pub mod kline;
 pub mod liquidation;
+pub mod mark_price;
+pub mod market;
+pub mod open_interest;
 pub mod order_book;
+pub mod partial_depth;
+pub mod rest;
+pub mod sentiment;
+pub mod user_data;
 pub mod websocket;
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use log::{error, info};
+use serde::{Deserialize, Deserializer};
+use tokio::sync::{broadcast, mpsc};
+use tokio::time::MissedTickBehavior;
+
+use super::market::BinanceMarket;
+use super::rest::BinanceRestClient;
+use super::websocket::{apply_stream_commands, BinanceData, StreamCommand};
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize)]
+pub struct OpenInterestResponse {
+    pub symbol: String,
+    pub openInterest: String,
+    pub time: u64,
+}
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize)]
+pub struct OpenInterestHistResponse {
+    pub symbol: String,
+    pub sumOpenInterest: String,
+    pub sumOpenInterestValue: String,
+    #[serde(deserialize_with = "u64_from_str_or_number")]
+    pub timestamp: u64,
+}
+
+/// Open interest of a symbol at `time`. Only backfilled history carries the notional value.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct OpenInterestEvent {
+    pub symbol: String,
+    pub open_interest: String,               // Open interest in contracts
+    pub open_interest_value: Option<String>, // Notional value in quote asset
+    pub time: u64,                           // Unix time in milliseconds
+    pub market: BinanceMarket,
+}
+
+#[derive(Debug, Clone)]
+pub struct OpenInterestConfig {
+    pub interval: Duration,
+    // Period ("5m", "15m", ... "1d") and number of points to backfill on start
+    pub backfill: Option<(String, u16)>,
+}
+
+// The futures/data endpoints only serve the last 30 days in these periods
+pub const FUTURES_DATA_PERIODS: [&str; 9] =
+    ["5m", "15m", "30m", "1h", "2h", "4h", "6h", "12h", "1d"];
+
+pub fn u64_from_str_or_number<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum StrOrNumber {
+        Str(String),
+        Number(u64),
+    }
+
+    match StrOrNumber::deserialize(deserializer)? {
+        StrOrNumber::Str(s) => s.parse().map_err(serde::de::Error::custom),
+        StrOrNumber::Number(n) => Ok(n),
+    }
+}
+
+pub async fn fetch_open_interest(
+    client: &BinanceRestClient,
+    symbol: &str,
+    market: BinanceMarket,
+) -> Result<OpenInterestResponse, reqwest::Error> {
+    let url = format!(
+        "{}{}/openInterest?symbol={}",
+        market.rest_base(),
+        market.api_prefix(),
+        symbol.to_uppercase()
+    );
+
+    client
+        .get_json::<OpenInterestResponse>(market, &url, 1)
+        .await
+}
+
+pub async fn fetch_open_interest_hist(
+    client: &BinanceRestClient,
+    symbol: &str,
+    period: &str,
+    limit: u16,
+) -> Result<Vec<OpenInterestHistResponse>, reqwest::Error> {
+    let url = format!(
+        "https://fapi.binance.com/futures/data/openInterestHist?symbol={}&period={}&limit={}",
+        symbol.to_uppercase(),
+        period,
+        limit.min(500)
+    );
+
+    client
+        .get_json::<Vec<OpenInterestHistResponse>>(BinanceMarket::UsdM, &url, 1)
+        .await
+}
+
+/// Polls open interest for every symbol each `config.interval` and sends it
+/// into the same channel as the websocket handlers. Symbols are added and
+/// removed as `commands` announce listings and delistings.
+/// History backfill is only served for USD-M symbols.
+pub(crate) async fn poll_open_interest(
+    mut symbols: Vec<String>,
+    market: BinanceMarket,
+    config: OpenInterestConfig,
+    mut commands: broadcast::Receiver<StreamCommand>,
+    tx: mpsc::Sender<BinanceData>,
+) {
+    let client = BinanceRestClient::shared();
+
+    if let (Some((period, limit)), BinanceMarket::UsdM) = (&config.backfill, market) {
+        for symbol in &symbols {
+            match fetch_open_interest_hist(client, symbol, period, *limit).await {
+                Ok(history) => {
+                    info!(
+                        "Backfilled {} open interest points for {}",
+                        history.len(),
+                        symbol
+                    );
+                    for point in history {
+                        let event = OpenInterestEvent {
+                            symbol: point.symbol,
+                            open_interest: point.sumOpenInterest,
+                            open_interest_value: Some(point.sumOpenInterestValue),
+                            time: point.timestamp,
+                            market,
+                        };
+                        if tx.send(BinanceData::OpenInterest(event)).await.is_err() {
+                            return;
+                        }
+                    }
+                }
+                Err(e) => error!("Failed to backfill open interest for {}: {}", symbol, e),
+            }
+        }
+    }
+
+    // Binance only refreshes open interest every few seconds, skip unchanged readings
+    let mut last_times: HashMap<String, u64> = HashMap::new();
+    let mut ticker = tokio::time::interval(config.interval);
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        ticker.tick().await;
+        apply_stream_commands(&mut commands, &mut symbols);
+        for symbol in &symbols {
+            match fetch_open_interest(client, symbol, market).await {
+                Ok(response) => {
+                    if last_times.get(&response.symbol) == Some(&response.time) {
+                        continue;
+                    }
+                    last_times.insert(response.symbol.clone(), response.time);
+
+                    let event = OpenInterestEvent {
+                        symbol: response.symbol,
+                        open_interest: response.openInterest,
+                        open_interest_value: None,
+                        time: response.time,
+                        market,
+                    };
+                    if tx.send(BinanceData::OpenInterest(event)).await.is_err() {
+                        info!("Receiver dropped, stopping open interest poller");
+                        return;
+                    }
+                }
+                Err(e) => error!("Failed to fetch open interest for {}: {}", symbol, e),
+            }
+        }
+    }
+}
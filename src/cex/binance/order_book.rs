@@ -1,13 +1,27 @@
-use futures::{SinkExt, StreamExt};
-use log::{error, info};
+use std::collections::VecDeque;
+
+use log::{error, info, warn};
 use serde::Deserialize;
 use tokio::sync::mpsc;
-use tokio_tungstenite::tungstenite::Message;
 
-use crate::cex::combined_order_book::CombinedOrderBook;
+use crate::cex::backoff::Backoff;
+use crate::cex::combined_order_book::{OrderBook, Venue, VenueBook};
 
+use super::market::BinanceMarket;
+use super::rest::BinanceRestClient;
 use super::websocket::BinanceData;
 
+/// Update speeds of the depth streams over all markets.
+pub const DEPTH_SPEEDS: [&str; 4] = ["100ms", "250ms", "500ms", "1000ms"];
+
+/// Update speeds `market` pushes depth at, its default first.
+pub fn depth_speeds(market: BinanceMarket) -> &'static [&'static str] {
+    match market {
+        BinanceMarket::UsdM | BinanceMarket::CoinM => &["250ms", "100ms", "500ms"],
+        BinanceMarket::Spot => &["1000ms", "100ms"],
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Deserialize)]
 pub struct BinanceWebsocketDiffBook {
@@ -15,6 +29,14 @@ pub struct BinanceWebsocketDiffBook {
     pub data: DepthEvent,
 }
 
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct BinanceWebsocketSpotDiffBook {
+    pub stream: String,
+    pub data: SpotDepthEvent,
+}
+
+/// Diff depth event of USD-M and COIN-M futures.
 #[allow(dead_code, non_snake_case)]
 #[derive(Debug, Deserialize)]
 pub struct DepthEvent {
@@ -29,98 +51,454 @@ pub struct DepthEvent {
     pub a: Vec<(String, String)>, // Asks to update
 }
 
+/// Diff depth event of spot, which has no transaction time nor `pu`.
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize)]
+pub struct SpotDepthEvent {
+    pub e: String,                // Event type
+    pub E: u64,                   // Event time
+    pub s: String,                // Symbol
+    pub U: u64,                   // First update ID in event
+    pub u: u64,                   // Final update ID in event
+    pub b: Vec<(String, String)>, // Bids to update
+    pub a: Vec<(String, String)>, // Asks to update
+}
+
+/// Diff depth event of any market, as applied to the local order book.
+#[derive(Debug)]
+pub struct DepthUpdate {
+    pub event_time: u64,
+    pub first_update_id: u64,
+    pub final_update_id: u64,
+    pub previous_final_update_id: Option<u64>, // Futures only
+    pub bids: Vec<(String, String)>,
+    pub asks: Vec<(String, String)>,
+}
+
+impl From<DepthEvent> for DepthUpdate {
+    fn from(event: DepthEvent) -> Self {
+        Self {
+            event_time: event.E,
+            first_update_id: event.U,
+            final_update_id: event.u,
+            previous_final_update_id: Some(event.pu),
+            bids: event.b,
+            asks: event.a,
+        }
+    }
+}
+
+impl From<SpotDepthEvent> for DepthUpdate {
+    fn from(event: SpotDepthEvent) -> Self {
+        Self {
+            event_time: event.E,
+            first_update_id: event.U,
+            final_update_id: event.u,
+            previous_final_update_id: None,
+            bids: event.b,
+            asks: event.a,
+        }
+    }
+}
+
 #[allow(dead_code, non_snake_case)]
 #[derive(Debug, Deserialize)]
 pub struct DepthSnapShot {
     pub lastUpdateId: u64,
-    pub E: u64, // Message output time
-    pub T: u64, // Transaction time
+    #[serde(default)]
+    pub E: u64, // Message output time, missing on spot
+    #[serde(default)]
+    pub T: u64, // Transaction time, missing on spot
     pub bids: Vec<(String, String)>,
     pub asks: Vec<(String, String)>,
 }
 
-pub async fn fetch_depth_snapshot(symbol: &str) -> Result<DepthSnapShot, reqwest::Error> {
+pub async fn fetch_depth_snapshot(
+    symbol: &str,
+    market: BinanceMarket,
+) -> Result<DepthSnapShot, reqwest::Error> {
     let url = format!(
-        "https://fapi.binance.com/fapi/v1/depth?symbol={}&limit=1000",
-        symbol
+        "{}{}/depth?symbol={}&limit=1000",
+        market.rest_base(),
+        market.api_prefix(),
+        symbol.to_uppercase()
     );
 
-    let client = reqwest::Client::new();
-
-    let response = client
-        .get(&url)
-        .timeout(std::time::Duration::from_secs(10))
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<DepthSnapShot>()
-        .await?;
-
-    Ok(response)
-}
-
-pub async fn handle_order_book<R, S>(
-    mut read: R,
-    mut write: S,
-    order_book: &mut CombinedOrderBook,
-    snapshot: DepthSnapShot,
-    tx: mpsc::Sender<BinanceData>,
-) where
-    R: StreamExt<Item = Result<Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
-    S: SinkExt<Message> + Unpin,
-    S::Error: std::fmt::Display,
-{
-    let mut last_update_id = snapshot.lastUpdateId;
-
-    // Initialize the order book with the snapshot data
-    for (price, quantity) in snapshot.bids.iter().chain(snapshot.asks.iter()) {
-        if price.parse::<f64>().is_err() || quantity.parse::<f64>().is_err() {
-            error!(
-                "Invalid snapshot data: price={}, quantity={}",
-                price, quantity
-            );
-            continue;
+    // Weight of a 1000 level snapshot
+    let weight = if market.is_futures() { 20 } else { 50 };
+    BinanceRestClient::shared()
+        .get_json::<DepthSnapShot>(market, &url, weight)
+        .await
+}
+
+/// Spawns a task that fetches a depth snapshot for `stream`, retrying with
+/// backoff until it succeeds or the connection that asked for it is gone.
+pub fn request_depth_snapshot(
+    stream: String,
+    symbol: String,
+    market: BinanceMarket,
+    snapshot_tx: mpsc::Sender<(String, DepthSnapShot)>,
+) {
+    tokio::spawn(async move {
+        let mut backoff = Backoff::default();
+        loop {
+            match fetch_depth_snapshot(&symbol, market).await {
+                Ok(snapshot) => {
+                    // The connection may have dropped in the meantime
+                    let _ = snapshot_tx.send((stream, snapshot)).await;
+                    return;
+                }
+                Err(e) => error!("Failed to fetch depth snapshot for {}: {}", symbol, e),
+            }
+
+            if snapshot_tx.is_closed() {
+                return;
+            }
+            tokio::time::sleep(backoff.next_delay()).await;
+        }
+    });
+}
+
+// Events older than this many diffs are dropped while waiting for a snapshot
+const MAX_BUFFERED_EVENTS: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncStatus {
+    Synced,
+    Pending,
+    Resync, // The update chain broke: a new snapshot must be fetched
+}
+
+#[derive(Debug)]
+enum SyncState {
+    // Diff events are buffered until the REST snapshot returns
+    AwaitingSnapshot { buffer: VecDeque<DepthUpdate> },
+    // Snapshot applied, waiting for the event that straddles its lastUpdateId
+    Bridging { last_update_id: u64 },
+    Synced { last_update_id: u64 },
+}
+
+/// Local order book of a single `@depth` stream, kept in sync with the
+/// documented procedure of its market:
+///
+/// 1. Buffer diff events while the REST snapshot is being fetched.
+/// 2. Drop every event with `u < lastUpdateId` (spot: `u <= lastUpdateId`).
+/// 3. The first applied event must satisfy `U <= lastUpdateId <= u`
+///    (spot: `U <= lastUpdateId + 1 <= u`).
+/// 4. Every following event must satisfy `pu == previous u`
+///    (spot: `U == previous u + 1`).
+///
+/// Whenever step 3 or 4 fails the book is discarded and rebuilt from a new snapshot.
+pub struct OrderBookState {
+    symbol: String,
+    market: BinanceMarket,
+    order_book: OrderBook,
+    state: SyncState,
+}
+
+impl OrderBookState {
+    pub fn new(symbol: &str, market: BinanceMarket) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            market,
+            order_book: OrderBook::new(),
+            state: SyncState::AwaitingSnapshot {
+                buffer: VecDeque::new(),
+            },
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub async fn handle_order_book(
+        &mut self,
+        text: &str,
+        tx: &mpsc::Sender<BinanceData>,
+    ) -> SyncStatus {
+        let update = match self.market {
+            BinanceMarket::Spot => serde_json::from_str::<BinanceWebsocketSpotDiffBook>(text)
+                .map(|event| DepthUpdate::from(event.data)),
+            _ => serde_json::from_str::<BinanceWebsocketDiffBook>(text)
+                .map(|event| DepthUpdate::from(event.data)),
+        };
+
+        match update {
+            Ok(update) => self.handle_update(update, tx).await,
+            Err(e) => {
+                error!("Failed to parse event: {} - Error: {}", text, e);
+                self.status()
+            }
         }
     }
 
-    order_book.bids.extend(snapshot.bids.into_iter());
-    order_book.asks.extend(snapshot.asks.into_iter());
-
-    while let Some(msg) = read.next().await {
-        match msg {
-            Ok(Message::Text(text)) => {
-                match serde_json::from_str::<BinanceWebsocketDiffBook>(&text) {
-                    Ok(event) => {
-                        if event.data.u <= last_update_id || event.data.U <= last_update_id {
-                            error!("Event out of order: Reinitializing");
-                            continue;
-                        }
-                        order_book.update_binance(&event.data);
-
-                        if tx
-                            .send(BinanceData::OrderBook(order_book.clone()))
-                            .await
-                            .is_err()
-                        {
-                            error!("Failed to send order book update");
-                        }
-                        last_update_id = event.data.u;
-                    }
-                    Err(e) => error!("Failed to parse event: {} - Error: {}", text, e),
+    /// Rebuilds the book from `snapshot` and replays the buffered events on top of it.
+    pub async fn apply_snapshot(
+        &mut self,
+        snapshot: DepthSnapShot,
+        tx: &mpsc::Sender<BinanceData>,
+    ) -> SyncStatus {
+        let buffer = match &mut self.state {
+            SyncState::AwaitingSnapshot { buffer } => std::mem::take(buffer),
+            _ => {
+                warn!("Ignoring unexpected depth snapshot for {}", self.symbol);
+                return self.status();
+            }
+        };
+
+        // Initialize the order book with the snapshot data, invalid levels are skipped
+        self.order_book
+            .apply_snapshot(snapshot.E, &snapshot.bids, &snapshot.asks);
+        self.state = SyncState::Bridging {
+            last_update_id: snapshot.lastUpdateId,
+        };
+        info!(
+            "Applied depth snapshot for {} at {}, replaying {} buffered events",
+            self.symbol,
+            snapshot.lastUpdateId,
+            buffer.len()
+        );
+
+        let mut buffer = buffer.into_iter();
+        while let Some(update) = buffer.next() {
+            if self.handle_update(update, tx).await == SyncStatus::Resync {
+                // The events after the break are needed to bridge the next snapshot
+                if let SyncState::AwaitingSnapshot { buffer: pending } = &mut self.state {
+                    pending.extend(buffer);
+                }
+                return SyncStatus::Resync;
+            }
+        }
+        self.status()
+    }
+
+    fn status(&self) -> SyncStatus {
+        match self.state {
+            SyncState::Synced { .. } => SyncStatus::Synced,
+            _ => SyncStatus::Pending,
+        }
+    }
+
+    async fn handle_update(
+        &mut self,
+        update: DepthUpdate,
+        tx: &mpsc::Sender<BinanceData>,
+    ) -> SyncStatus {
+        let futures = self.market.is_futures();
+        match &mut self.state {
+            SyncState::AwaitingSnapshot { buffer } => {
+                if buffer.len() >= MAX_BUFFERED_EVENTS {
+                    buffer.pop_front();
                 }
+                buffer.push_back(update);
+                return SyncStatus::Pending;
             }
-            Ok(Message::Ping(payload)) => {
-                if let Err(e) = write.send(Message::Pong(payload)).await {
-                    error!("Failed to send Pong: {}", e);
+            SyncState::Bridging { last_update_id } => {
+                let (stale, bridges) = if futures {
+                    (
+                        update.final_update_id < *last_update_id,
+                        update.first_update_id <= *last_update_id,
+                    )
+                } else {
+                    (
+                        update.final_update_id <= *last_update_id,
+                        update.first_update_id <= *last_update_id + 1,
+                    )
+                };
+                if stale {
+                    return SyncStatus::Pending;
+                }
+                if !bridges {
+                    error!(
+                        "Depth event {}..{} for {} does not bridge snapshot {}: Reinitializing",
+                        update.first_update_id, update.final_update_id, self.symbol, last_update_id
+                    );
+                    return self.reinitialize(update);
                 }
             }
-            Ok(Message::Pong(_)) => info!("Received Pong"),
-            Ok(Message::Close(reason)) => {
-                info!("WebSocket closed: {:?}", reason);
-                break;
+            SyncState::Synced { last_update_id } => {
+                if update.final_update_id <= *last_update_id {
+                    // Already applied, e.g. replayed while bridging
+                    return SyncStatus::Synced;
+                }
+                let continues = match update.previous_final_update_id {
+                    Some(pu) => pu == *last_update_id,
+                    None => update.first_update_id == *last_update_id + 1,
+                };
+                if !continues {
+                    error!(
+                        "Depth event out of order for {} (U={}, pu={:?}, previous u={}): Reinitializing",
+                        self.symbol,
+                        update.first_update_id,
+                        update.previous_final_update_id,
+                        last_update_id
+                    );
+                    return self.reinitialize(update);
+                }
             }
-            Err(e) => error!("Error reading message: {}", e),
-            _ => (),
         }
+
+        self.order_book
+            .apply_update(update.event_time, &update.bids, &update.asks);
+        self.state = SyncState::Synced {
+            last_update_id: update.final_update_id,
+        };
+
+        let venue_book = VenueBook {
+            symbol: self.symbol.to_uppercase(),
+            venue: Venue::Binance(self.market),
+            book: self.order_book.clone(),
+        };
+        if tx.send(BinanceData::OrderBook(venue_book)).await.is_err() {
+            error!("Failed to send order book update");
+        }
+        SyncStatus::Synced
+    }
+
+    /// Drops the book and starts buffering again, beginning with `update`.
+    fn reinitialize(&mut self, update: DepthUpdate) -> SyncStatus {
+        self.order_book.clear();
+        self.state = SyncState::AwaitingSnapshot {
+            buffer: VecDeque::from([update]),
+        };
+        SyncStatus::Resync
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(first: u64, last: u64, previous: Option<u64>) -> DepthUpdate {
+        DepthUpdate {
+            event_time: last,
+            first_update_id: first,
+            final_update_id: last,
+            previous_final_update_id: previous,
+            bids: vec![("100.0".to_string(), "1.0".to_string())],
+            asks: vec![("101.0".to_string(), "1.0".to_string())],
+        }
+    }
+
+    fn snapshot(last_update_id: u64) -> DepthSnapShot {
+        DepthSnapShot {
+            lastUpdateId: last_update_id,
+            E: 0,
+            T: 0,
+            bids: vec![("99.0".to_string(), "2.0".to_string())],
+            asks: vec![("102.0".to_string(), "2.0".to_string())],
+        }
+    }
+
+    fn buffered(state: &OrderBookState) -> Vec<u64> {
+        match &state.state {
+            SyncState::AwaitingSnapshot { buffer } => {
+                buffer.iter().map(|update| update.first_update_id).collect()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// A book at `last_update_id` waiting for its bridging event.
+    async fn bridging(
+        market: BinanceMarket,
+        last_update_id: u64,
+        tx: &mpsc::Sender<BinanceData>,
+    ) -> OrderBookState {
+        let mut state = OrderBookState::new("btcusdt", market);
+        assert_eq!(
+            state.apply_snapshot(snapshot(last_update_id), tx).await,
+            SyncStatus::Pending
+        );
+        state
+    }
+
+    #[tokio::test]
+    async fn futures_drops_events_before_snapshot() {
+        let (tx, _rx) = mpsc::channel(16);
+        let mut state = bridging(BinanceMarket::UsdM, 100, &tx).await;
+
+        let status = state.handle_update(update(90, 99, Some(89)), &tx).await;
+        assert_eq!(status, SyncStatus::Pending);
+        // u == lastUpdateId is the first event to apply on futures
+        let status = state.handle_update(update(95, 100, Some(94)), &tx).await;
+        assert_eq!(status, SyncStatus::Synced);
+    }
+
+    #[tokio::test]
+    async fn spot_drops_events_up_to_snapshot() {
+        let (tx, _rx) = mpsc::channel(16);
+        let mut state = bridging(BinanceMarket::Spot, 100, &tx).await;
+
+        let status = state.handle_update(update(95, 100, None), &tx).await;
+        assert_eq!(status, SyncStatus::Pending);
+        let status = state.handle_update(update(101, 105, None), &tx).await;
+        assert_eq!(status, SyncStatus::Synced);
+    }
+
+    #[tokio::test]
+    async fn first_event_must_straddle_snapshot() {
+        let (tx, _rx) = mpsc::channel(16);
+        let mut state = bridging(BinanceMarket::UsdM, 100, &tx).await;
+        let status = state.handle_update(update(98, 105, Some(97)), &tx).await;
+        assert_eq!(status, SyncStatus::Synced);
+
+        let mut state = bridging(BinanceMarket::UsdM, 100, &tx).await;
+        let status = state.handle_update(update(101, 105, Some(100)), &tx).await;
+        assert_eq!(status, SyncStatus::Resync);
+        assert_eq!(buffered(&state), vec![101]);
+    }
+
+    #[tokio::test]
+    async fn futures_resyncs_on_pu_gap() {
+        let (tx, _rx) = mpsc::channel(16);
+        let mut state = bridging(BinanceMarket::UsdM, 100, &tx).await;
+        state.handle_update(update(98, 105, Some(97)), &tx).await;
+
+        let status = state.handle_update(update(106, 110, Some(105)), &tx).await;
+        assert_eq!(status, SyncStatus::Synced);
+        let status = state.handle_update(update(112, 115, Some(111)), &tx).await;
+        assert_eq!(status, SyncStatus::Resync);
+        assert_eq!(buffered(&state), vec![112]);
+    }
+
+    #[tokio::test]
+    async fn spot_resyncs_on_update_id_gap() {
+        let (tx, _rx) = mpsc::channel(16);
+        let mut state = bridging(BinanceMarket::Spot, 100, &tx).await;
+        state.handle_update(update(98, 105, None), &tx).await;
+
+        let status = state.handle_update(update(106, 110, None), &tx).await;
+        assert_eq!(status, SyncStatus::Synced);
+        let status = state.handle_update(update(112, 115, None), &tx).await;
+        assert_eq!(status, SyncStatus::Resync);
+    }
+
+    #[tokio::test]
+    async fn buffer_keeps_latest_events() {
+        let (tx, _rx) = mpsc::channel(16);
+        let mut state = OrderBookState::new("btcusdt", BinanceMarket::UsdM);
+        for id in 0..=MAX_BUFFERED_EVENTS as u64 {
+            let status = state.handle_update(update(id, id, None), &tx).await;
+            assert_eq!(status, SyncStatus::Pending);
+        }
+
+        let buffer = buffered(&state);
+        assert_eq!(buffer.len(), MAX_BUFFERED_EVENTS);
+        assert_eq!(buffer.first(), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn replay_break_keeps_remaining_events() {
+        let (tx, _rx) = mpsc::channel(16);
+        let mut state = OrderBookState::new("btcusdt", BinanceMarket::UsdM);
+        state.handle_update(update(95, 105, Some(94)), &tx).await;
+        state.handle_update(update(107, 110, Some(106)), &tx).await;
+        state.handle_update(update(111, 115, Some(110)), &tx).await;
+
+        let status = state.apply_snapshot(snapshot(100), &tx).await;
+        assert_eq!(status, SyncStatus::Resync);
+        assert_eq!(buffered(&state), vec![107, 111]);
     }
 }
@@ -0,0 +1,105 @@
+use log::error;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use super::market::BinanceMarket;
+use super::order_book::DepthEvent;
+use super::websocket::BinanceData;
+
+pub const PARTIAL_DEPTH_LEVELS: [u8; 3] = [5, 10, 20];
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct BinanceWebsocketPartialDepth {
+    pub stream: String,
+    pub data: DepthEvent,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct BinanceWebsocketSpotPartialDepth {
+    pub stream: String,
+    pub data: SpotPartialDepth,
+}
+
+/// Spot partial depth payload, which has neither symbol nor event time.
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize)]
+pub struct SpotPartialDepth {
+    pub lastUpdateId: u64,
+    pub bids: Vec<(String, String)>,
+    pub asks: Vec<(String, String)>,
+}
+
+/// Top `levels` bids and asks of a `@depth<levels>` stream. Each event is a
+/// complete top-N book, so no local order book is maintained for it.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct PartialDepthEvent {
+    pub symbol: String, // Uppercase symbol
+    pub market: BinanceMarket,
+    pub levels: u8,
+    pub last_update_id: u64,
+    pub event_time: u64,             // Local receive time on spot
+    pub bids: Vec<(String, String)>, // Best first
+    pub asks: Vec<(String, String)>, // Best first
+}
+
+/// Parses the level count out of a stream name such as "btcusdt@depth20@100ms".
+pub fn partial_depth_levels(stream: &str) -> Option<u8> {
+    let channel = stream.split('@').nth(1)?;
+    channel
+        .strip_prefix("depth")?
+        .parse::<u8>()
+        .ok()
+        .filter(|levels| PARTIAL_DEPTH_LEVELS.contains(levels))
+}
+
+pub async fn handle_partial_depth(
+    text: &str,
+    stream: &str,
+    market: BinanceMarket,
+    tx: &mpsc::Sender<BinanceData>,
+) {
+    let Some(levels) = partial_depth_levels(stream) else {
+        error!("Invalid partial depth stream {}", stream);
+        return;
+    };
+
+    let event = match market {
+        BinanceMarket::Spot => {
+            serde_json::from_str::<BinanceWebsocketSpotPartialDepth>(text).map(|event| {
+                PartialDepthEvent {
+                    // Only the stream name tells which symbol this is
+                    symbol: stream.split('@').next().unwrap_or_default().to_uppercase(),
+                    market,
+                    levels,
+                    last_update_id: event.data.lastUpdateId,
+                    event_time: chrono::Utc::now().timestamp_millis() as u64,
+                    bids: event.data.bids,
+                    asks: event.data.asks,
+                }
+            })
+        }
+        _ => serde_json::from_str::<BinanceWebsocketPartialDepth>(text).map(|event| {
+            PartialDepthEvent {
+                symbol: event.data.s,
+                market,
+                levels,
+                last_update_id: event.data.u,
+                event_time: event.data.E,
+                bids: event.data.b,
+                asks: event.data.a,
+            }
+        }),
+    };
+
+    match event {
+        Ok(event) => {
+            if tx.send(BinanceData::PartialDepth(event)).await.is_err() {
+                error!("Failed to send partial depth event");
+            }
+        }
+        Err(e) => error!("Failed to parse event: {} - Error: {}", text, e),
+    }
+}
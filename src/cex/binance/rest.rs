@@ -0,0 +1,235 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use log::warn;
+use reqwest::{header::HeaderMap, RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use tokio::time::{sleep, Duration, Instant};
+
+use super::market::BinanceMarket;
+
+// Only this share of the per minute weight is spent, leaving room for other clients on the IP
+const WEIGHT_BUDGET: f64 = 0.9;
+
+// Background requests leave this share of the budget to snapshots and pollers
+const BACKGROUND_RESERVE: f64 = 0.5;
+
+// Used when a 429/418 response doesn't say how long to back off
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Token bucket over the request weight Binance allows per minute and IP.
+#[derive(Debug)]
+struct WeightBucket {
+    capacity: f64,
+    tokens: f64,
+    refilled_at: Instant,
+    blocked_until: Option<Instant>,
+}
+
+impl WeightBucket {
+    fn new(market: BinanceMarket) -> Self {
+        let capacity = market.weight_limit_per_minute() as f64 * WEIGHT_BUDGET;
+        Self {
+            capacity,
+            tokens: capacity,
+            refilled_at: Instant::now(),
+            blocked_until: None,
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.capacity / 60.0).min(self.capacity);
+        self.refilled_at = now;
+    }
+
+    /// Takes `weight` tokens if `reserve` (a share of the capacity) is left
+    /// afterwards, or returns how long to wait before trying again.
+    fn try_take(&mut self, weight: u32, reserve: f64, now: Instant) -> Result<(), Duration> {
+        if let Some(until) = self.blocked_until {
+            if until > now {
+                return Err(until - now);
+            }
+            self.blocked_until = None;
+        }
+
+        self.refill(now);
+        // A request heavier than the whole budget still goes through once the bucket is full
+        let weight = (weight as f64).min(self.capacity);
+        let needed = (weight + reserve * self.capacity).min(self.capacity);
+        if self.tokens >= needed {
+            self.tokens -= weight;
+            Ok(())
+        } else {
+            let missing = needed - self.tokens;
+            Err(Duration::from_secs_f64(missing * 60.0 / self.capacity))
+        }
+    }
+
+    /// Aligns the bucket with the weight Binance reports as used in the current minute,
+    /// which also covers requests made by other processes on the same IP.
+    fn observe_used_weight(&mut self, used: u32) {
+        let remaining = (self.capacity - used as f64).max(0.0);
+        self.tokens = self.tokens.min(remaining);
+    }
+
+    fn block_for(&mut self, duration: Duration) {
+        let until = Instant::now() + duration;
+        self.blocked_until = Some(
+            self.blocked_until
+                .map_or(until, |current| current.max(until)),
+        );
+        self.tokens = 0.0;
+    }
+}
+
+/// REST client shared by snapshot fetches, pollers and backfills, so that all
+/// of them spend the same weight budget of each market.
+#[derive(Debug)]
+pub struct BinanceRestClient {
+    http: reqwest::Client,
+    buckets: Mutex<HashMap<BinanceMarket, WeightBucket>>,
+}
+
+#[allow(dead_code)]
+impl BinanceRestClient {
+    pub fn new() -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Process wide client.
+    pub fn shared() -> &'static BinanceRestClient {
+        static CLIENT: OnceLock<BinanceRestClient> = OnceLock::new();
+        CLIENT.get_or_init(BinanceRestClient::new)
+    }
+
+    pub fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+
+    /// GETs `url` and parses the JSON body, spending `weight` of the market's budget.
+    pub async fn get_json<T: DeserializeOwned>(
+        &self,
+        market: BinanceMarket,
+        url: &str,
+        weight: u32,
+    ) -> Result<T, reqwest::Error> {
+        self.send(market, self.http.get(url), weight)
+            .await?
+            .json::<T>()
+            .await
+    }
+
+    /// Like `get_json`, but only spends `weight` while half of the budget stays
+    /// available, so bulk backfills never hold up depth snapshots or pollers.
+    pub async fn get_json_background<T: DeserializeOwned>(
+        &self,
+        market: BinanceMarket,
+        url: &str,
+        weight: u32,
+    ) -> Result<T, reqwest::Error> {
+        self.send_reserving(market, self.http.get(url), weight, BACKGROUND_RESERVE)
+            .await?
+            .json::<T>()
+            .await
+    }
+
+    /// Sends `request` once `weight` is available in the market's budget.
+    /// 429 and 418 responses pause every request of the market for `Retry-After`.
+    pub async fn send(
+        &self,
+        market: BinanceMarket,
+        request: RequestBuilder,
+        weight: u32,
+    ) -> Result<Response, reqwest::Error> {
+        self.send_reserving(market, request, weight, 0.0).await
+    }
+
+    async fn send_reserving(
+        &self,
+        market: BinanceMarket,
+        request: RequestBuilder,
+        weight: u32,
+        reserve: f64,
+    ) -> Result<Response, reqwest::Error> {
+        self.acquire(market, weight, reserve).await;
+
+        let response = request.timeout(Duration::from_secs(10)).send().await?;
+        self.observe(market, response.status(), response.headers());
+        response.error_for_status()
+    }
+
+    async fn acquire(&self, market: BinanceMarket, weight: u32, reserve: f64) {
+        loop {
+            let wait = {
+                let mut buckets = self.buckets.lock().unwrap();
+                let bucket = buckets
+                    .entry(market)
+                    .or_insert_with(|| WeightBucket::new(market));
+                match bucket.try_take(weight, reserve, Instant::now()) {
+                    Ok(()) => return,
+                    Err(wait) => wait,
+                }
+            };
+            sleep(wait).await;
+        }
+    }
+
+    fn observe(&self, market: BinanceMarket, status: StatusCode, headers: &HeaderMap) {
+        let used_weight = headers
+            .get("X-MBX-USED-WEIGHT-1M")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u32>().ok());
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets
+            .entry(market)
+            .or_insert_with(|| WeightBucket::new(market));
+
+        if let Some(used) = used_weight {
+            bucket.observe_used_weight(used);
+        }
+
+        // 429: rate limited, 418: IP banned for ignoring 429s
+        if status == StatusCode::TOO_MANY_REQUESTS || status == StatusCode::IM_A_TEAPOT {
+            let retry_after = headers
+                .get(reqwest::header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok())
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_RETRY_AFTER);
+            warn!(
+                "Binance {} REST returned {}, pausing requests for {:?}",
+                market, status, retry_after
+            );
+            bucket.block_for(retry_after);
+        }
+    }
+}
+
+impl Default for BinanceRestClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn background_requests_leave_the_reserve() {
+        let mut bucket = WeightBucket::new(BinanceMarket::UsdM);
+        let now = bucket.refilled_at;
+        let capacity = bucket.capacity;
+        bucket.tokens = capacity * BACKGROUND_RESERVE + 20.0;
+
+        assert!(bucket.try_take(20, BACKGROUND_RESERVE, now).is_ok());
+        assert!(bucket.try_take(20, BACKGROUND_RESERVE, now).is_err());
+        // Snapshots and pollers still get the reserve
+        assert!(bucket.try_take(20, 0.0, now).is_ok());
+    }
+}
@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use log::{error, info};
+use serde::{de::DeserializeOwned, Deserialize};
+use tokio::sync::{broadcast, mpsc};
+
+use super::market::BinanceMarket;
+use super::open_interest::u64_from_str_or_number;
+use super::rest::BinanceRestClient;
+use super::websocket::{apply_stream_commands, BinanceData, StreamCommand};
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize)]
+pub struct LongShortRatioResponse {
+    pub symbol: String,
+    pub longShortRatio: String,
+    pub longAccount: String, // Long share of accounts or positions, depending on the endpoint
+    pub shortAccount: String,
+    #[serde(deserialize_with = "u64_from_str_or_number")]
+    pub timestamp: u64,
+}
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize)]
+pub struct TakerVolumeResponse {
+    pub buySellRatio: String,
+    pub buyVol: String,
+    pub sellVol: String,
+    #[serde(deserialize_with = "u64_from_str_or_number")]
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LongShortRatioKind {
+    GlobalAccount, // All accounts
+    TopAccount,    // Top 20% traders by margin, counted by account
+    TopPosition,   // Top 20% traders by margin, weighted by position size
+}
+
+impl LongShortRatioKind {
+    pub const ALL: [LongShortRatioKind; 3] = [
+        LongShortRatioKind::GlobalAccount,
+        LongShortRatioKind::TopAccount,
+        LongShortRatioKind::TopPosition,
+    ];
+
+    fn path(&self) -> &'static str {
+        match self {
+            LongShortRatioKind::GlobalAccount => "/futures/data/globalLongShortAccountRatio",
+            LongShortRatioKind::TopAccount => "/futures/data/topLongShortAccountRatio",
+            LongShortRatioKind::TopPosition => "/futures/data/topLongShortPositionRatio",
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LongShortRatioKind::GlobalAccount => "global_account",
+            LongShortRatioKind::TopAccount => "top_account",
+            LongShortRatioKind::TopPosition => "top_position",
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct LongShortRatioEvent {
+    pub symbol: String,
+    pub kind: LongShortRatioKind,
+    pub period: String,
+    pub long_short_ratio: String,
+    pub long_share: String,
+    pub short_share: String,
+    pub time: u64, // Unix time in milliseconds
+    pub market: BinanceMarket,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct TakerVolumeEvent {
+    pub symbol: String,
+    pub period: String,
+    pub buy_sell_ratio: String,
+    pub buy_volume: String,
+    pub sell_volume: String,
+    pub time: u64, // Unix time in milliseconds
+    pub market: BinanceMarket,
+}
+
+// Points requested per poll; older ones are dropped by the timestamp check
+const SENTIMENT_LIMIT: u16 = 30;
+
+// Binance publishes a period's numbers a little after it closes
+const PUBLISH_DELAY: Duration = Duration::from_secs(15);
+
+pub fn period_duration(period: &str) -> Option<Duration> {
+    let (value, unit) = period.split_at(period.len().checked_sub(1)?);
+    let value = value.parse::<u64>().ok()?;
+    let seconds = match unit {
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        _ => return None,
+    };
+    Some(Duration::from_secs(value * seconds))
+}
+
+async fn fetch_futures_data<T: DeserializeOwned>(
+    client: &BinanceRestClient,
+    path: &str,
+    symbol: &str,
+    period: &str,
+) -> Result<Vec<T>, reqwest::Error> {
+    let url = format!(
+        "https://fapi.binance.com{}?symbol={}&period={}&limit={}",
+        path,
+        symbol.to_uppercase(),
+        period,
+        SENTIMENT_LIMIT
+    );
+
+    client
+        .get_json::<Vec<T>>(BinanceMarket::UsdM, &url, 1)
+        .await
+}
+
+/// Polls long/short ratios and taker buy/sell volume for every symbol once
+/// per `period`, shortly after each period closes. Points already sent are
+/// skipped by timestamp. Only USD-M symbols are served. Symbols are added
+/// and removed as `commands` announce listings and delistings.
+pub(crate) async fn poll_sentiment(
+    mut symbols: Vec<String>,
+    period: String,
+    mut commands: broadcast::Receiver<StreamCommand>,
+    tx: mpsc::Sender<BinanceData>,
+) {
+    let Some(every) = period_duration(&period) else {
+        error!("Unsupported sentiment period {}", period);
+        return;
+    };
+    let client = BinanceRestClient::shared();
+    let mut last_ratio_times: HashMap<(String, LongShortRatioKind), u64> = HashMap::new();
+    let mut last_taker_times: HashMap<String, u64> = HashMap::new();
+
+    loop {
+        apply_stream_commands(&mut commands, &mut symbols);
+        for symbol in &symbols {
+            for kind in LongShortRatioKind::ALL {
+                let points = match fetch_futures_data::<LongShortRatioResponse>(
+                    client,
+                    kind.path(),
+                    symbol,
+                    &period,
+                )
+                .await
+                {
+                    Ok(points) => points,
+                    Err(e) => {
+                        error!("Failed to fetch {} for {}: {}", kind.as_str(), symbol, e);
+                        continue;
+                    }
+                };
+
+                let last_time = last_ratio_times.entry((symbol.clone(), kind)).or_default();
+                for point in points {
+                    if point.timestamp <= *last_time {
+                        continue;
+                    }
+                    *last_time = point.timestamp;
+
+                    let event = LongShortRatioEvent {
+                        symbol: point.symbol,
+                        kind,
+                        period: period.clone(),
+                        long_short_ratio: point.longShortRatio,
+                        long_share: point.longAccount,
+                        short_share: point.shortAccount,
+                        time: point.timestamp,
+                        market: BinanceMarket::UsdM,
+                    };
+                    if tx.send(BinanceData::LongShortRatio(event)).await.is_err() {
+                        info!("Receiver dropped, stopping sentiment poller");
+                        return;
+                    }
+                }
+            }
+
+            match fetch_futures_data::<TakerVolumeResponse>(
+                client,
+                "/futures/data/takerlongshortRatio",
+                symbol,
+                &period,
+            )
+            .await
+            {
+                Ok(points) => {
+                    let last_time = last_taker_times.entry(symbol.clone()).or_default();
+                    for point in points {
+                        if point.timestamp <= *last_time {
+                            continue;
+                        }
+                        *last_time = point.timestamp;
+
+                        let event = TakerVolumeEvent {
+                            symbol: symbol.to_uppercase(),
+                            period: period.clone(),
+                            buy_sell_ratio: point.buySellRatio,
+                            buy_volume: point.buyVol,
+                            sell_volume: point.sellVol,
+                            time: point.timestamp,
+                            market: BinanceMarket::UsdM,
+                        };
+                        if tx.send(BinanceData::TakerVolume(event)).await.is_err() {
+                            info!("Receiver dropped, stopping sentiment poller");
+                            return;
+                        }
+                    }
+                }
+                Err(e) => error!("Failed to fetch taker volume for {}: {}", symbol, e),
+            }
+        }
+
+        // Sleep until the current period closes and its numbers are published
+        let now = chrono::Utc::now().timestamp_millis() as u64;
+        let every_ms = every.as_millis() as u64;
+        let next_close = (now / every_ms + 1) * every_ms;
+        tokio::time::sleep(Duration::from_millis(next_close - now) + PUBLISH_DELAY).await;
+    }
+}
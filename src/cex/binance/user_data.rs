@@ -0,0 +1,570 @@
+use log::{error, info, warn};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio::time::{interval, sleep, Duration, MissedTickBehavior};
+
+use crate::cex::backoff::Backoff;
+use crate::cex::connection::{
+    spawn_reader, ConnectionConfig, Reader, ReaderMessage, STABLE_CONNECTION,
+};
+
+use super::market::BinanceMarket;
+use super::rest::BinanceRestClient;
+use super::websocket::BinanceData;
+
+// Binance closes listen keys that were not kept alive for 60 minutes
+const LISTEN_KEY_KEEPALIVE: Duration = Duration::from_secs(30 * 60);
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize)]
+pub struct ListenKeyResponse {
+    pub listenKey: String,
+}
+
+/// Private account event of the user data stream.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub enum UserDataEvent {
+    OrderTradeUpdate(Box<OrderTradeUpdateEvent>),
+    AccountUpdate(AccountUpdateEvent),
+    MarginCall(MarginCallEvent),
+}
+
+#[allow(dead_code)]
+impl UserDataEvent {
+    /// Symbol of an order update; account wide events have none.
+    pub fn symbol(&self) -> Option<&str> {
+        match self {
+            UserDataEvent::OrderTradeUpdate(event) => Some(&event.o.s),
+            UserDataEvent::AccountUpdate(_) | UserDataEvent::MarginCall(_) => None,
+        }
+    }
+
+    pub fn market(&self) -> BinanceMarket {
+        match self {
+            UserDataEvent::OrderTradeUpdate(event) => event.market,
+            UserDataEvent::AccountUpdate(event) => event.market,
+            UserDataEvent::MarginCall(event) => event.market,
+        }
+    }
+}
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct OrderTradeUpdateEvent {
+    pub E: u64, // Event time
+    pub T: u64, // Transaction time
+    pub o: OrderUpdate,
+    #[serde(skip)]
+    pub market: BinanceMarket,
+}
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct OrderUpdate {
+    pub s: String,         // Symbol
+    pub c: String,         // Client order ID
+    pub S: String,         // Side
+    pub o: String,         // Order type
+    pub f: String,         // Time in force
+    pub q: String,         // Original quantity
+    pub p: String,         // Original price
+    pub ap: String,        // Average price
+    pub sp: String,        // Stop price
+    pub x: String,         // Execution type
+    pub X: String,         // Order status
+    pub i: u64,            // Order ID
+    pub l: String,         // Last filled quantity
+    pub z: String,         // Filled accumulated quantity
+    pub L: String,         // Last filled price
+    pub N: Option<String>, // Commission asset, absent without a fill
+    pub n: Option<String>, // Commission, absent without a fill
+    pub T: u64,            // Trade time
+    pub t: u64,            // Trade ID
+    pub m: bool,           // Is this trade the maker side?
+    pub R: bool,           // Is this reduce only
+    pub ps: String,        // Position side
+    pub rp: String,        // Realized profit of the trade
+}
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct AccountUpdateEvent {
+    pub E: u64, // Event time
+    pub T: u64, // Transaction time
+    pub a: AccountUpdate,
+    #[serde(skip)]
+    pub market: BinanceMarket,
+}
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct AccountUpdate {
+    pub m: String, // Reason type, e.g. "ORDER" or "FUNDING_FEE"
+    pub B: Vec<BalanceUpdate>,
+    pub P: Vec<PositionUpdate>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct BalanceUpdate {
+    pub a: String,  // Asset
+    pub wb: String, // Wallet balance
+    pub cw: String, // Cross wallet balance
+    pub bc: String, // Balance change except PnL and commission
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct PositionUpdate {
+    pub s: String,  // Symbol
+    pub pa: String, // Position amount
+    pub ep: String, // Entry price
+    pub cr: String, // Accumulated realized
+    pub up: String, // Unrealized PnL
+    pub mt: String, // Margin type
+    pub iw: String, // Isolated wallet
+    pub ps: String, // Position side
+}
+
+#[allow(dead_code, non_snake_case)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct MarginCallEvent {
+    pub E: u64,             // Event time
+    pub cw: Option<String>, // Cross wallet balance, only for crossed positions
+    pub p: Vec<MarginCallPosition>,
+    #[serde(skip)]
+    pub market: BinanceMarket,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, Clone)]
+pub struct MarginCallPosition {
+    pub s: String,  // Symbol
+    pub ps: String, // Position side
+    pub pa: String, // Position amount
+    pub mt: String, // Margin type
+    pub iw: String, // Isolated wallet
+    pub mp: String, // Mark price
+    pub up: String, // Unrealized PnL
+    pub mm: String, // Maintenance margin required
+}
+
+/// Only the event type is read here; the typed parsers take the full payload.
+#[derive(Debug, Deserialize)]
+struct UserDataFrame {
+    e: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct UserDataStreamBuilder {
+    api_key: String,
+    market: BinanceMarket,
+    rest_base: Option<String>,
+    ws_base: Option<String>,
+    keepalive_interval: Duration,
+}
+
+#[allow(dead_code)]
+impl UserDataStreamBuilder {
+    pub fn new(api_key: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            market: BinanceMarket::default(),
+            rest_base: None,
+            ws_base: None,
+            keepalive_interval: LISTEN_KEY_KEEPALIVE,
+        }
+    }
+
+    /// Futures market of the account (USD-M by default).
+    pub fn with_market(mut self, market: BinanceMarket) -> Self {
+        self.market = market;
+        self
+    }
+
+    /// Overrides the REST base URL, e.g. "http://127.0.0.1:8080".
+    pub fn with_rest_base(mut self, url: &str) -> Self {
+        self.rest_base = Some(url.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Overrides the websocket base URL, e.g. "ws://127.0.0.1:8081".
+    pub fn with_ws_base(mut self, url: &str) -> Self {
+        self.ws_base = Some(url.trim_end_matches('/').to_string());
+        self
+    }
+
+    /// Keeps the listen key alive every `interval` instead of every 30 minutes.
+    pub fn with_keepalive_interval(mut self, interval: Duration) -> Self {
+        self.keepalive_interval = interval;
+        self
+    }
+
+    pub async fn build(
+        self,
+        tx: mpsc::Sender<BinanceData>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.market.is_futures() {
+            return Err(format!("User data stream is not supported on {}", self.market).into());
+        }
+
+        let client = UserDataClient {
+            listen_key_url: format!(
+                "{}{}/listenKey",
+                self.rest_base.as_deref().unwrap_or(self.market.rest_base()),
+                self.market.api_prefix()
+            ),
+            ws_base: self
+                .ws_base
+                .unwrap_or_else(|| self.market.ws_base().to_string()),
+            api_key: self.api_key,
+            market: self.market,
+            keepalive_interval: self.keepalive_interval,
+        };
+
+        info!("Starting Binance {} user data stream", client.market);
+        tokio::spawn(async move {
+            client.supervise(tx).await;
+        });
+        Ok(())
+    }
+}
+
+struct UserDataClient {
+    listen_key_url: String,
+    ws_base: String,
+    api_key: String,
+    market: BinanceMarket,
+    keepalive_interval: Duration,
+}
+
+impl UserDataClient {
+    async fn create_listen_key(&self) -> Result<String, reqwest::Error> {
+        let rest = BinanceRestClient::shared();
+        let request = rest
+            .http()
+            .post(&self.listen_key_url)
+            .header("X-MBX-APIKEY", &self.api_key);
+        let response = rest
+            .send(self.market, request, 1)
+            .await?
+            .json::<ListenKeyResponse>()
+            .await?;
+        Ok(response.listenKey)
+    }
+
+    /// Extends the validity of the current listen key by 60 minutes.
+    async fn keepalive_listen_key(&self) -> Result<(), reqwest::Error> {
+        let rest = BinanceRestClient::shared();
+        let request = rest
+            .http()
+            .put(&self.listen_key_url)
+            .header("X-MBX-APIKEY", &self.api_key);
+        rest.send(self.market, request, 1).await?;
+        Ok(())
+    }
+
+    async fn close_listen_key(&self) -> Result<(), reqwest::Error> {
+        let rest = BinanceRestClient::shared();
+        let request = rest
+            .http()
+            .delete(&self.listen_key_url)
+            .header("X-MBX-APIKEY", &self.api_key);
+        rest.send(self.market, request, 1).await?;
+        Ok(())
+    }
+
+    /// Creates a listen key and streams it until the key expires or the socket
+    /// drops or stalls. An expired key is renewed right away, failures are
+    /// retried with a fresh key after a backoff delay.
+    async fn supervise(&self, tx: mpsc::Sender<BinanceData>) {
+        let mut backoff = Backoff::default();
+        let (frame_tx, mut frame_rx) = mpsc::channel::<ReaderMessage>(256);
+        let mut next_id = 0;
+
+        loop {
+            let mut expired = false;
+            match self.create_listen_key().await {
+                Ok(listen_key) => {
+                    next_id += 1;
+                    let ws_url = format!("{}/ws/{}", self.ws_base, listen_key);
+                    // Account events can be hours apart, a dead socket eventually fails its pings
+                    let config = ConnectionConfig {
+                        stall_timeout: None,
+                        ..ConnectionConfig::default()
+                    };
+                    match spawn_reader(next_id, &ws_url, config, frame_tx.clone()).await {
+                        Ok(reader) => {
+                            info!("Connected to Binance {} user data stream", self.market);
+                            expired = self.run_connection(&reader, &mut frame_rx, &tx).await;
+
+                            if reader.connected_at.elapsed() >= STABLE_CONNECTION {
+                                backoff.reset();
+                            }
+                        }
+                        Err(e) => error!("Failed to connect to Binance user data stream: {}", e),
+                    }
+                }
+                Err(e) => error!("Failed to create listen key: {}", e),
+            }
+
+            if tx.is_closed() {
+                info!("Receiver dropped, stopping Binance user data stream");
+                if let Err(e) = self.close_listen_key().await {
+                    error!("Failed to close listen key: {}", e);
+                }
+                return;
+            }
+            if expired {
+                continue;
+            }
+
+            let delay = backoff.next_delay();
+            warn!(
+                "Binance user data stream dropped, reconnecting in {:?} (attempt {})",
+                delay,
+                backoff.attempt()
+            );
+            sleep(delay).await;
+        }
+    }
+
+    /// Returns true when the connection ended because the listen key expired.
+    async fn run_connection(
+        &self,
+        reader: &Reader,
+        frame_rx: &mut mpsc::Receiver<ReaderMessage>,
+        tx: &mpsc::Sender<BinanceData>,
+    ) -> bool {
+        let mut keepalive = interval(self.keepalive_interval);
+        keepalive.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        // The first tick completes immediately and the key was just created
+        keepalive.tick().await;
+
+        loop {
+            tokio::select! {
+                msg = frame_rx.recv() => match msg {
+                    Some(ReaderMessage::Frame(id, text)) if id == reader.id => {
+                        let listen_key_valid = handle_user_data(&text, self.market, tx).await;
+                        if !listen_key_valid {
+                            warn!("Listen key expired, renewing user data stream");
+                            return true;
+                        }
+                    }
+                    Some(ReaderMessage::Closed(id)) if id == reader.id => return false,
+                    Some(_) => (),
+                    None => return false,
+                },
+                _ = keepalive.tick() => {
+                    if let Err(e) = self.keepalive_listen_key().await {
+                        // The key may be gone already, so start over with a new one
+                        error!("Failed to keep listen key alive: {}", e);
+                        return false;
+                    }
+                    info!("Kept Binance {} listen key alive", self.market);
+                }
+            }
+        }
+    }
+}
+
+/// Parses a user data payload and forwards it to `tx`.
+/// Returns false when the listen key has expired and the stream must be renewed.
+pub async fn handle_user_data(
+    text: &str,
+    market: BinanceMarket,
+    tx: &mpsc::Sender<BinanceData>,
+) -> bool {
+    let frame = match serde_json::from_str::<UserDataFrame>(text) {
+        Ok(frame) => frame,
+        Err(e) => {
+            error!("Failed to parse event: {} - Error: {}", text, e);
+            return true;
+        }
+    };
+
+    let event = match frame.e.as_str() {
+        "ORDER_TRADE_UPDATE" => serde_json::from_str::<OrderTradeUpdateEvent>(text).map(|mut e| {
+            e.market = market;
+            UserDataEvent::OrderTradeUpdate(Box::new(e))
+        }),
+        "ACCOUNT_UPDATE" => serde_json::from_str::<AccountUpdateEvent>(text).map(|mut e| {
+            e.market = market;
+            UserDataEvent::AccountUpdate(e)
+        }),
+        "MARGIN_CALL" => serde_json::from_str::<MarginCallEvent>(text).map(|mut e| {
+            e.market = market;
+            UserDataEvent::MarginCall(e)
+        }),
+        "listenKeyExpired" => return false,
+        other => {
+            info!("Ignoring user data event {}", other);
+            return true;
+        }
+    };
+
+    match event {
+        Ok(event) => {
+            if tx.send(BinanceData::UserData(event)).await.is_err() {
+                error!("Failed to send user data event");
+            }
+        }
+        Err(e) => error!("Failed to parse event: {} - Error: {}", text, e),
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use futures::{SinkExt, StreamExt};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::Notify;
+    use tokio::time::timeout;
+    use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+    use tokio_tungstenite::tungstenite::Message;
+
+    use super::*;
+
+    const ORDER_TRADE_UPDATE: &str = r#"{"e":"ORDER_TRADE_UPDATE","E":1568879465651,"T":1568879465650,"o":{"s":"BTCUSDT","c":"TEST","S":"SELL","o":"TRAILING_STOP_MARKET","f":"GTC","q":"0.001","p":"0","ap":"0","sp":"7103.04","x":"NEW","X":"NEW","i":8886774,"l":"0","z":"0","L":"0","T":1568879465650,"t":0,"b":"0","a":"9.91","m":false,"R":false,"wt":"CONTRACT_PRICE","ot":"TRAILING_STOP_MARKET","ps":"LONG","cp":false,"AP":"7476.89","cr":"5.0","rp":"0"}}"#;
+
+    const LISTEN_KEY_EXPIRED: &str =
+        r#"{"e":"listenKeyExpired","E":1576653824250,"listenKey":"key1"}"#;
+
+    /// Answers listen key requests, handing out "key1", "key2", ... on POST.
+    /// Logs "<method> <path> (<API key>)" of every request.
+    async fn serve_listen_keys(listener: TcpListener, log: mpsc::UnboundedSender<String>) {
+        let mut created = 0;
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buffer = [0u8; 1024];
+            while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                match socket.read(&mut buffer).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => request.extend_from_slice(&buffer[..n]),
+                }
+            }
+            let request = String::from_utf8_lossy(&request);
+            let api_key = request
+                .lines()
+                .find_map(|line| {
+                    let (name, value) = line.split_once(':')?;
+                    name.eq_ignore_ascii_case("x-mbx-apikey")
+                        .then(|| value.trim())
+                })
+                .unwrap_or_default();
+
+            let mut line = request.lines().next().unwrap_or_default().split(' ');
+            let method = line.next().unwrap_or_default();
+            let path = line.next().unwrap_or_default();
+            let body = if method == "POST" {
+                created += 1;
+                format!(r#"{{"listenKey":"key{}"}}"#, created)
+            } else {
+                String::from("{}")
+            };
+            let _ = log.send(format!("{} {} ({})", method, path, api_key));
+
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    }
+
+    /// Sends an order update on every connection and `listenKeyExpired` once
+    /// `expire` is notified. Logs "WS <path>" of every connection.
+    async fn serve_user_data(
+        listener: TcpListener,
+        log: mpsc::UnboundedSender<String>,
+        expire: Arc<Notify>,
+    ) {
+        while let Ok((socket, _)) = listener.accept().await {
+            let mut path = String::new();
+            // The callback signature, with its large error type, is fixed by tungstenite
+            #[allow(clippy::result_large_err)]
+            let callback = |request: &Request, response: Response| {
+                path = request.uri().path().to_string();
+                Ok(response)
+            };
+            let Ok(mut ws) = tokio_tungstenite::accept_hdr_async(socket, callback).await else {
+                continue;
+            };
+            let _ = log.send(format!("WS {}", path));
+
+            let expire = expire.clone();
+            tokio::spawn(async move {
+                let _ = ws.send(Message::Text(ORDER_TRADE_UPDATE.into())).await;
+                expire.notified().await;
+                let _ = ws.send(Message::Text(LISTEN_KEY_EXPIRED.into())).await;
+                while let Some(Ok(_)) = ws.next().await {}
+            });
+        }
+    }
+
+    async fn next_request(log: &mut mpsc::UnboundedReceiver<String>) -> String {
+        timeout(Duration::from_secs(5), log.recv())
+            .await
+            .expect("no request to the mock server")
+            .unwrap_or_default()
+    }
+
+    #[tokio::test]
+    async fn listen_key_lifecycle() {
+        let rest = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let rest_base = format!("http://{}", rest.local_addr().unwrap());
+        let ws_base = format!("ws://{}", ws.local_addr().unwrap());
+        let (log_tx, mut log_rx) = mpsc::unbounded_channel();
+        let expire = Arc::new(Notify::new());
+        tokio::spawn(serve_listen_keys(rest, log_tx.clone()));
+        tokio::spawn(serve_user_data(ws, log_tx, expire.clone()));
+
+        let (tx, mut rx) = mpsc::channel(16);
+        UserDataStreamBuilder::new("test-key")
+            .with_rest_base(&rest_base)
+            .with_ws_base(&ws_base)
+            .with_keepalive_interval(Duration::from_millis(100))
+            .build(tx)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            next_request(&mut log_rx).await,
+            "POST /fapi/v1/listenKey (test-key)"
+        );
+        assert_eq!(next_request(&mut log_rx).await, "WS /ws/key1");
+
+        let event = timeout(Duration::from_secs(5), rx.recv()).await.unwrap();
+        let Some(BinanceData::UserData(UserDataEvent::OrderTradeUpdate(event))) = event else {
+            panic!("expected an order update, got {:?}", event);
+        };
+        assert_eq!(event.market, BinanceMarket::UsdM);
+        assert_eq!(event.o.s, "BTCUSDT");
+        assert_eq!(event.o.i, 8886774);
+        assert_eq!(event.o.X, "NEW");
+        assert_eq!(event.o.N, None);
+
+        assert_eq!(
+            next_request(&mut log_rx).await,
+            "PUT /fapi/v1/listenKey (test-key)"
+        );
+
+        // A new key is created and streamed once the current one expires,
+        // without waiting for the reconnect backoff (at least 500ms)
+        let expired_at = tokio::time::Instant::now();
+        expire.notify_one();
+        let mut request = next_request(&mut log_rx).await;
+        while request.starts_with("PUT") {
+            request = next_request(&mut log_rx).await;
+        }
+        assert_eq!(request, "POST /fapi/v1/listenKey (test-key)");
+        assert_eq!(next_request(&mut log_rx).await, "WS /ws/key2");
+        assert!(expired_at.elapsed() < Duration::from_millis(400));
+    }
+}
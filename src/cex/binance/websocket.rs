@@ -1,92 +1,961 @@
-use futures::StreamExt;
-use log::info;
-use tokio::sync::mpsc;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
+use log::{error, info, warn};
+use serde::Deserialize;
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::time::{sleep, sleep_until, Duration, Instant};
+use tokio_tungstenite::tungstenite;
+
+use crate::cex::backoff::Backoff;
 use crate::cex::binance::{
     agg_trade::{handle_agg_trade, AggregateTradeEvent},
+    agg_trade_backfill::AggTradeGaps,
+    book_ticker::{handle_book_ticker, BookTickerEvent},
+    contract_info::{handle_contract_info, ContractInfoEvent, CONTRACT_INFO_STREAM},
+    kline::{handle_continuous_kline, handle_kline, KlineEvent, KLINE_INTERVALS},
     liquidation::{handle_liquidation_order, LiquidationEvent},
-    order_book::{fetch_depth_snapshot, handle_order_book},
+    mark_price::{handle_mark_price, MarkPriceEvent},
+    market::BinanceMarket,
+    open_interest::{
+        poll_open_interest, OpenInterestConfig, OpenInterestEvent, FUTURES_DATA_PERIODS,
+    },
+    order_book::{
+        depth_speeds, request_depth_snapshot, DepthSnapShot, OrderBookState, SyncStatus,
+        DEPTH_SPEEDS,
+    },
+    partial_depth::{
+        handle_partial_depth, partial_depth_levels, PartialDepthEvent, PARTIAL_DEPTH_LEVELS,
+    },
+    sentiment::{poll_sentiment, LongShortRatioEvent, TakerVolumeEvent},
+    user_data::UserDataEvent,
+};
+use crate::cex::combined_order_book::VenueBook;
+use crate::cex::connection::{
+    spawn_reader, ConnectionConfig, Reader, ReaderMessage, RecentFrames, STABLE_CONNECTION,
 };
-use crate::cex::combined_order_book::CombinedOrderBook;
+
+// Wait before retrying a rotation whose replacement failed to connect
+const ROTATION_RETRY: Duration = Duration::from_secs(60);
+
+// Recent payloads remembered to drop duplicates delivered while rotating
+const RECENT_FRAMES: usize = 10_000;
+
+const ALL_MARKET_LIQUIDATIONS: &str = "!forceOrder@arr";
+
+// Symbol reported for streams that cover the whole market, e.g. "!forceOrder@arr"
+pub const ALL_MARKET_SYMBOL: &str = "ALL";
 
 #[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub enum BinanceData {
-    OrderBook(CombinedOrderBook),
+    OrderBook(VenueBook),
+    PartialDepth(PartialDepthEvent),
     Liquidation(LiquidationEvent),
     AggTrade(AggregateTradeEvent),
+    MarkPrice(MarkPriceEvent),
+    Kline(KlineEvent),
+    BookTicker(BookTickerEvent),
+    ContractInfo(ContractInfoEvent),
+    OpenInterest(OpenInterestEvent),
+    LongShortRatio(LongShortRatioEvent),
+    TakerVolume(TakerVolumeEvent),
+    UserData(UserDataEvent),
+    Reconnect(ReconnectEvent),
+}
+
+#[allow(dead_code)]
+impl BinanceData {
+    /// Uppercase symbol (e.g. "BTCUSDT") the event belongs to.
+    pub fn symbol(&self) -> &str {
+        match self {
+            BinanceData::OrderBook(order_book) => &order_book.symbol,
+            BinanceData::PartialDepth(event) => &event.symbol,
+            BinanceData::Liquidation(event) => &event.o.s,
+            BinanceData::AggTrade(event) => &event.s,
+            BinanceData::MarkPrice(event) => &event.s,
+            BinanceData::Kline(event) => &event.s,
+            BinanceData::BookTicker(event) => &event.s,
+            BinanceData::ContractInfo(event) => &event.s,
+            BinanceData::OpenInterest(event) => &event.symbol,
+            BinanceData::LongShortRatio(event) => &event.symbol,
+            BinanceData::TakerVolume(event) => &event.symbol,
+            BinanceData::UserData(event) => event.symbol().unwrap_or(ALL_MARKET_SYMBOL),
+            BinanceData::Reconnect(event) => &event.symbol,
+        }
+    }
+
+    pub fn market(&self) -> BinanceMarket {
+        match self {
+            BinanceData::OrderBook(order_book) => {
+                order_book.venue.binance_market().unwrap_or_default()
+            }
+            BinanceData::PartialDepth(event) => event.market,
+            BinanceData::Liquidation(event) => event.market,
+            BinanceData::AggTrade(event) => event.market,
+            BinanceData::MarkPrice(event) => event.market,
+            BinanceData::Kline(event) => event.market,
+            BinanceData::BookTicker(event) => event.market,
+            BinanceData::ContractInfo(event) => event.market,
+            BinanceData::OpenInterest(event) => event.market,
+            BinanceData::LongShortRatio(event) => event.market,
+            BinanceData::TakerVolume(event) => event.market,
+            BinanceData::UserData(event) => event.market(),
+            BinanceData::Reconnect(event) => event.market,
+        }
+    }
+}
+
+/// Emitted after a dropped stream is connected again.
+/// Data between `disconnected_at` and `reconnected_at` may be missing.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct ReconnectEvent {
+    pub symbol: String,
+    pub market: BinanceMarket,
+    pub streams: Vec<String>, // Streams of `symbol` that were interrupted
+    pub attempt: u32,
+    pub disconnected_at: u64, // Unix time in milliseconds
+    pub reconnected_at: u64,  // Unix time in milliseconds
+}
+
+/// Sent by the contract lifecycle task to every connection supervisor and poller.
+#[derive(Debug, Clone)]
+pub(crate) enum StreamCommand {
+    // Unsubscribe every stream of the uppercase symbol and stop polling it
+    Remove(String),
+    // Start polling the uppercase symbol, which streams over its own connection
+    Add(String),
+}
+
+/// Applies the commands received since the last call to the lowercase
+/// `symbols` a poller requests.
+pub(crate) fn apply_stream_commands(
+    commands: &mut broadcast::Receiver<StreamCommand>,
+    symbols: &mut Vec<String>,
+) {
+    loop {
+        match commands.try_recv() {
+            Ok(StreamCommand::Remove(symbol)) => {
+                symbols.retain(|s| !s.eq_ignore_ascii_case(&symbol));
+            }
+            Ok(StreamCommand::Add(symbol)) => {
+                if !symbols.iter().any(|s| s.eq_ignore_ascii_case(&symbol)) {
+                    symbols.push(symbol.to_lowercase());
+                }
+            }
+            Err(broadcast::error::TryRecvError::Lagged(missed)) => {
+                warn!("Missed {} contract lifecycle commands", missed);
+            }
+            Err(_) => return,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct BinanceStreamBuilder {
-    symbol: String,
+    market: BinanceMarket,
+    symbols: Vec<String>,
     streams: Vec<String>,
+    open_interest: Option<OpenInterestConfig>,
+    sentiment_period: Option<String>,
+    auto_subscribe: Option<String>,
+    last_agg_trade_ids: HashMap<String, u64>,
+    connection: ConnectionConfig,
 }
 
 #[allow(dead_code)]
 impl BinanceStreamBuilder {
     pub fn new(symbol: &str) -> Self {
+        Self::with_symbols(&[symbol])
+    }
+
+    /// Collects the same streams for every symbol over shared connections.
+    pub fn with_symbols<S: AsRef<str>>(symbols: &[S]) -> Self {
         Self {
-            symbol: symbol.to_lowercase().to_string(),
+            market: BinanceMarket::default(),
+            symbols: symbols
+                .iter()
+                .map(|symbol| symbol.as_ref().to_lowercase())
+                .collect(),
             streams: Vec::new(),
+            open_interest: None,
+            sentiment_period: None,
+            auto_subscribe: None,
+            last_agg_trade_ids: HashMap::new(),
+            connection: ConnectionConfig::default(),
         }
     }
 
-    pub fn with_depth(mut self) -> Self {
-        self.streams.push(format!("{}@depth", self.symbol));
+    /// Selects the market all streams and pollers connect to (USD-M by default).
+    /// Streams the market doesn't offer are dropped when building.
+    pub fn with_market(mut self, market: BinanceMarket) -> Self {
+        self.market = market;
+        self
+    }
+
+    /// Reconnects when a connection delivers no data for `timeout`. Pongs don't
+    /// count, so streams that can stay quiet need a longer duration.
+    pub fn with_stall_timeout(mut self, timeout: Duration) -> Self {
+        self.connection.stall_timeout = Some(timeout);
         self
     }
 
-    pub fn with_liquidation(mut self) -> Self {
-        self.streams.push(format!("{}@forceOrder", self.symbol));
+    /// Pings the server every `interval`, so quiet streams still prove they are alive.
+    pub fn with_ping_interval(mut self, interval: Duration) -> Self {
+        self.connection.ping_interval = Some(interval);
         self
     }
 
-    pub fn with_agg_trade(mut self) -> Self {
-        self.streams.push(format!("{}@aggTrade", self.symbol));
+    /// Replaces each connection after `interval`, before Binance cuts it at 24 hours.
+    pub fn with_rotation_interval(mut self, interval: Duration) -> Self {
+        self.connection.rotation_interval = interval;
+        self
+    }
+
+    /// Diff depth maintained into a full local order book, pushed at the
+    /// market's default speed.
+    pub fn with_depth(self) -> Self {
+        self.with_stream("depth")
+    }
+
+    /// Like `with_depth`, with diffs pushed every `speed`: "100ms", "250ms" or
+    /// "500ms" on futures, "100ms" or "1000ms" on spot. Speeds the market
+    /// doesn't offer are dropped when building.
+    pub fn with_depth_speed(self, speed: &str) -> Self {
+        if !DEPTH_SPEEDS.contains(&speed) {
+            error!("Unsupported depth speed {}", speed);
+            return self;
+        }
+        self.with_stream(&format!("depth@{}", speed))
+    }
+
+    /// Top `levels` (5, 10 or 20) bids and asks pushed every `speed`, with the
+    /// same speeds as `with_depth_speed`.
+    /// No REST snapshot is fetched and no local book is kept for these streams.
+    pub fn with_partial_depth(self, levels: u8, speed: &str) -> Self {
+        if !PARTIAL_DEPTH_LEVELS.contains(&levels) {
+            error!("Unsupported partial depth levels {}", levels);
+            return self;
+        }
+        if !DEPTH_SPEEDS.contains(&speed) {
+            error!("Unsupported depth speed {}", speed);
+            return self;
+        }
+        self.with_stream(&format!("depth{}@{}", levels, speed))
+    }
+
+    pub fn with_liquidation(self) -> Self {
+        if self.streams.iter().any(|s| s == ALL_MARKET_LIQUIDATIONS) {
+            warn!("All market liquidations already subscribed, skipping per-symbol streams");
+            return self;
+        }
+        self.with_stream("forceOrder")
+    }
+
+    /// Liquidations of every symbol over a single stream, replacing per-symbol
+    /// `@forceOrder` streams. Binance pushes at most one liquidation per symbol
+    /// every 1000ms on this stream.
+    pub fn all_market_liquidations(mut self) -> Self {
+        self.streams.retain(|s| !s.ends_with("@forceOrder"));
+        if !self.streams.iter().any(|s| s == ALL_MARKET_LIQUIDATIONS) {
+            self.streams.push(ALL_MARKET_LIQUIDATIONS.to_string());
+        }
+        self
+    }
+
+    pub fn with_agg_trade(self) -> Self {
+        self.with_stream("aggTrade")
+    }
+
+    /// Resumes agg trades from the last stored ID of each symbol (uppercase
+    /// symbol -> ID): the trades before the first live one are backfilled.
+    pub fn with_last_agg_trade_ids(mut self, last_ids: HashMap<String, u64>) -> Self {
+        self.last_agg_trade_ids = last_ids;
+        self
+    }
+
+    /// Mark price, index price and funding rate, pushed every second.
+    pub fn with_mark_price(self) -> Self {
+        self.with_stream("markPrice@1s")
+    }
+
+    /// Best bid/ask price and quantity, pushed on every top-of-book change.
+    pub fn with_book_ticker(self) -> Self {
+        self.with_stream("bookTicker")
+    }
+
+    /// Listings, settlements and leverage bracket changes of every contract.
+    /// Streams of symbols that go to SETTLING or CLOSE are unsubscribed and
+    /// the symbols are no longer polled.
+    pub fn with_contract_info(mut self) -> Self {
+        if !self.streams.iter().any(|s| s == CONTRACT_INFO_STREAM) {
+            self.streams.push(CONTRACT_INFO_STREAM.to_string());
+        }
+        self
+    }
+
+    /// Subscribes perpetuals listed while running whose symbol ends with
+    /// `suffix` (e.g. "USDT") to the streams of the first symbol, and adds
+    /// them to the REST pollers. Implies `with_contract_info`.
+    pub fn with_auto_subscribe(mut self, suffix: &str) -> Self {
+        self.auto_subscribe = Some(suffix.to_uppercase());
+        self.with_contract_info()
+    }
+
+    /// Polls open interest over REST every `interval`, since Binance doesn't stream it.
+    pub fn with_open_interest(mut self, interval: Duration) -> Self {
+        let backfill = self.open_interest.take().and_then(|config| config.backfill);
+        self.open_interest = Some(OpenInterestConfig { interval, backfill });
+        self
+    }
+
+    /// Backfills the last `limit` open interest points of `period` (e.g. "5m")
+    /// before polling starts. Requires `with_open_interest`.
+    pub fn with_open_interest_backfill(mut self, period: &str, limit: u16) -> Self {
+        if !FUTURES_DATA_PERIODS.contains(&period) {
+            error!("Unsupported open interest period {}", period);
+            return self;
+        }
+        match self.open_interest.as_mut() {
+            Some(config) => config.backfill = Some((period.to_string(), limit)),
+            None => error!("Open interest backfill requires with_open_interest"),
+        }
+        self
+    }
+
+    /// Polls long/short account and position ratios and taker buy/sell volume
+    /// of `period` (e.g. "5m") each time a period closes.
+    pub fn with_sentiment(mut self, period: &str) -> Self {
+        if !FUTURES_DATA_PERIODS.contains(&period) {
+            error!("Unsupported sentiment period {}", period);
+            return self;
+        }
+        self.sentiment_period = Some(period.to_string());
+        self
+    }
+
+    /// Candles of `interval` (e.g. "1m", "1h"), updated every 250ms until closed.
+    pub fn with_kline(self, interval: &str) -> Self {
+        if !KLINE_INTERVALS.contains(&interval) {
+            error!("Unsupported kline interval {}", interval);
+            return self;
+        }
+        self.with_stream(&format!("kline_{}", interval))
+    }
+
+    /// Candles of the perpetual contract of each symbol's pair.
+    pub fn with_continuous_kline(mut self, interval: &str) -> Self {
+        if !KLINE_INTERVALS.contains(&interval) {
+            error!("Unsupported kline interval {}", interval);
+            return self;
+        }
+        for symbol in &self.symbols {
+            self.streams
+                .push(format!("{}_perpetual@continuousKline_{}", symbol, interval));
+        }
+        self
+    }
+
+    fn with_stream(mut self, name: &str) -> Self {
+        for symbol in &self.symbols {
+            self.streams.push(format!("{}@{}", symbol, name));
+        }
         self
     }
 
     pub async fn build(
-        self,
+        mut self,
         tx: mpsc::Sender<BinanceData>,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        let market = self.market;
+        if !market.is_futures() {
+            self.streams.retain(|stream| {
+                let supported = !is_futures_only(stream);
+                if !supported {
+                    error!("Stream {} is not available on {}, skipping", stream, market);
+                }
+                supported
+            });
+        }
+        self.streams
+            .retain_mut(|stream| match market_depth_stream(stream, market) {
+                Some(resolved) => {
+                    *stream = resolved;
+                    true
+                }
+                None => {
+                    error!("Stream {} is not available on {}, skipping", stream, market);
+                    false
+                }
+            });
+
+        let connections = self.streams.chunks(market.max_streams_per_connection());
         info!(
-            "Starting {} Binance stream for {}",
+            "Starting {} Binance {} stream for {} over {} connection(s)",
             self.streams.len(),
-            self.symbol
+            market,
+            self.symbols.join(", "),
+            connections.len()
         );
 
-        for stream in &self.streams {
-            let ws_url = format!("wss://fstream.binance.com/stream?streams={}", stream);
-            let (ws_stream, _) = tokio_tungstenite::connect_async(&ws_url).await?;
-            let (write, read) = ws_stream.split();
-
-            match stream.as_str() {
-                s if s.ends_with("@depth") => {
-                    let symbol = self.symbol.clone();
-                    let tx_clone = tx.clone();
-                    tokio::spawn(async move {
-                        let snapshot = fetch_depth_snapshot(&symbol).await.unwrap();
-                        let mut order_book = CombinedOrderBook::new();
-                        handle_order_book(read, write, &mut order_book, snapshot, tx_clone).await;
-                    });
+        let connection = self.connection;
+        let (command_tx, _) = broadcast::channel::<StreamCommand>(64);
+        let contract_tx = if self.streams.iter().any(|s| s == CONTRACT_INFO_STREAM) {
+            let (contract_tx, contract_rx) = mpsc::channel::<ContractInfoEvent>(64);
+            let lifecycle = ContractLifecycle::new(
+                market,
+                &self.symbols,
+                &self.streams,
+                self.auto_subscribe.take(),
+                connection,
+                command_tx.clone(),
+            );
+            let tx_clone = tx.clone();
+            tokio::spawn(async move {
+                lifecycle.run(contract_rx, tx_clone).await;
+            });
+            Some(contract_tx)
+        } else {
+            None
+        };
+
+        for streams in connections {
+            let mut supervisor = ConnectionSupervisor::new(
+                market,
+                streams.to_vec(),
+                connection,
+                command_tx.subscribe(),
+                contract_tx.clone(),
+            );
+            supervisor
+                .agg_trade_gaps
+                .resume_from(&self.last_agg_trade_ids);
+            let tx_clone = tx.clone();
+            tokio::spawn(async move {
+                supervisor.run(tx_clone).await;
+            });
+        }
+
+        if let Some(config) = self.open_interest {
+            if market.is_futures() {
+                let symbols = self.symbols.clone();
+                let commands = command_tx.subscribe();
+                let tx_clone = tx.clone();
+                tokio::spawn(async move {
+                    poll_open_interest(symbols, market, config, commands, tx_clone).await;
+                });
+            } else {
+                error!("Open interest is not available on {}, skipping", market);
+            }
+        }
+
+        if let Some(period) = self.sentiment_period.filter(|_| {
+            let supported = market == BinanceMarket::UsdM;
+            if !supported {
+                error!("Sentiment data is only available on usdm, skipping");
+            }
+            supported
+        }) {
+            let symbols = self.symbols.clone();
+            let commands = command_tx.subscribe();
+            let tx_clone = tx.clone();
+            tokio::spawn(async move {
+                poll_sentiment(symbols, period, commands, tx_clone).await;
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Streams that only exist on the futures markets.
+fn is_futures_only(stream: &str) -> bool {
+    stream.ends_with("@forceOrder")
+        || stream == ALL_MARKET_LIQUIDATIONS
+        || stream == CONTRACT_INFO_STREAM
+        || stream.contains("@markPrice")
+        || stream.contains("@continuousKline_")
+}
+
+/// Name of a depth stream requested at a speed on `market`; the market's
+/// default speed has no suffix. `None` when `market` doesn't push depth at that speed.
+fn market_depth_stream(stream: &str, market: BinanceMarket) -> Option<String> {
+    let Some((name, speed)) = stream.rsplit_once('@').filter(|(_, speed)| {
+        stream_channel(stream).starts_with("depth") && DEPTH_SPEEDS.contains(speed)
+    }) else {
+        return Some(stream.to_string());
+    };
+
+    let speeds = depth_speeds(market);
+    if !speeds.contains(&speed) {
+        None
+    } else if speeds.first() == Some(&speed) {
+        Some(name.to_string())
+    } else {
+        Some(stream.to_string())
+    }
+}
+
+/// Channel part of a stream name, e.g. "depth20" for "btcusdt@depth20@100ms".
+fn stream_channel(stream: &str) -> &str {
+    stream.split('@').nth(1).unwrap_or_default()
+}
+
+/// Diff depth streams, which are synced into a local order book.
+fn is_diff_depth(stream: &str) -> bool {
+    stream_channel(stream) == "depth"
+}
+
+/// Wrapper every combined stream payload comes in, or the response to a
+/// SUBSCRIBE/UNSUBSCRIBE request, which has no stream.
+#[derive(Debug, Deserialize)]
+struct CombinedStreamFrame {
+    stream: Option<String>,
+    id: Option<u64>,
+    error: Option<serde_json::Value>,
+}
+
+/// Uppercase symbol of a stream name such as "btcusdt@depth" or
+/// "btcusdt_perpetual@continuousKline_1m". Market-wide streams give `ALL_MARKET_SYMBOL`.
+fn stream_symbol(stream: &str) -> String {
+    if stream.starts_with('!') {
+        return ALL_MARKET_SYMBOL.to_string();
+    }
+    stream
+        .split('@')
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches("_perpetual")
+        .to_uppercase()
+}
+
+/// Groups stream names by their uppercase symbol.
+fn streams_by_symbol(streams: &[String]) -> BTreeMap<String, Vec<String>> {
+    let mut grouped: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for stream in streams {
+        grouped
+            .entry(stream_symbol(stream))
+            .or_default()
+            .push(stream.clone());
+    }
+    grouped
+}
+
+/// Follows `!contractInfo`: tells the supervisors to drop the streams of
+/// symbols that stop trading, and subscribes matching new listings.
+struct ContractLifecycle {
+    market: BinanceMarket,
+    symbols: HashSet<String>, // Uppercase symbols currently streamed
+    templates: Vec<String>,   // Stream names of a symbol without the symbol, e.g. "@aggTrade"
+    auto_subscribe: Option<String>,
+    config: ConnectionConfig,
+    command_tx: broadcast::Sender<StreamCommand>,
+}
+
+impl ContractLifecycle {
+    fn new(
+        market: BinanceMarket,
+        symbols: &[String],
+        streams: &[String],
+        auto_subscribe: Option<String>,
+        config: ConnectionConfig,
+        command_tx: broadcast::Sender<StreamCommand>,
+    ) -> Self {
+        let templates = match symbols.first() {
+            Some(first) => streams
+                .iter()
+                .filter(|stream| stream_symbol(stream) == first.to_uppercase())
+                .filter_map(|stream| stream.strip_prefix(first.as_str()))
+                .map(str::to_string)
+                .collect(),
+            None => Vec::new(),
+        };
+        if auto_subscribe.is_some() && templates.is_empty() {
+            warn!("No per-symbol streams to auto subscribe new listings to");
+        }
+        Self {
+            market,
+            symbols: symbols.iter().map(|symbol| symbol.to_uppercase()).collect(),
+            templates,
+            auto_subscribe,
+            config,
+            command_tx,
+        }
+    }
+
+    async fn run(
+        mut self,
+        mut contract_rx: mpsc::Receiver<ContractInfoEvent>,
+        tx: mpsc::Sender<BinanceData>,
+    ) {
+        while let Some(event) = contract_rx.recv().await {
+            if event.is_closed() {
+                if self.symbols.remove(&event.s) {
+                    warn!(
+                        "{} {} is {}, closing its streams and pollers",
+                        self.market, event.s, event.cs
+                    );
+                    let _ = self.command_tx.send(StreamCommand::Remove(event.s));
                 }
-                s if s.ends_with("@forceOrder") => {
-                    let tx_clone = tx.clone();
-                    tokio::spawn(async move {
-                        handle_liquidation_order(read, write, tx_clone).await;
-                    });
+            } else if event.is_trading_perpetual()
+                && !self.symbols.contains(&event.s)
+                && !self.templates.is_empty()
+                && self
+                    .auto_subscribe
+                    .as_ref()
+                    .is_some_and(|suffix| event.s.ends_with(suffix.as_str()))
+            {
+                self.subscribe(event.s, &tx);
+            }
+        }
+    }
+
+    /// Streams a newly listed symbol over its own connection.
+    fn subscribe(&mut self, symbol: String, tx: &mpsc::Sender<BinanceData>) {
+        let lowercase = symbol.to_lowercase();
+        let streams: Vec<String> = self
+            .templates
+            .iter()
+            .map(|template| format!("{}{}", lowercase, template))
+            .collect();
+        info!(
+            "{} {} was listed, subscribing to {}",
+            self.market,
+            symbol,
+            streams.join(", ")
+        );
+        self.symbols.insert(symbol.clone());
+        let _ = self.command_tx.send(StreamCommand::Add(symbol));
+
+        let supervisor = ConnectionSupervisor::new(
+            self.market,
+            streams,
+            self.config,
+            self.command_tx.subscribe(),
+            None,
+        );
+        let tx_clone = tx.clone();
+        tokio::spawn(async move {
+            supervisor.run(tx_clone).await;
+        });
+    }
+}
+
+/// Keeps a combined-stream connection alive: connects, reads until the socket
+/// drops or stalls, then reconnects with jittered exponential backoff.
+/// Connections are rotated before the server's 24 hour cutoff without a gap.
+struct ConnectionSupervisor {
+    market: BinanceMarket,
+    streams: Vec<String>,
+    ws_url: String,
+    config: ConnectionConfig,
+    next_id: u64,
+    recent: RecentFrames,
+    // Outlives connections, so trades missed while reconnecting are detected
+    agg_trade_gaps: AggTradeGaps,
+    frame_tx: mpsc::Sender<ReaderMessage>,
+    frame_rx: mpsc::Receiver<ReaderMessage>,
+    commands: broadcast::Receiver<StreamCommand>,
+    // Where `!contractInfo` events go when this connection carries that stream
+    contract_tx: Option<mpsc::Sender<ContractInfoEvent>>,
+    next_request_id: u64,
+}
+
+impl ConnectionSupervisor {
+    fn new(
+        market: BinanceMarket,
+        streams: Vec<String>,
+        config: ConnectionConfig,
+        commands: broadcast::Receiver<StreamCommand>,
+        contract_tx: Option<mpsc::Sender<ContractInfoEvent>>,
+    ) -> Self {
+        let (frame_tx, frame_rx) = mpsc::channel::<ReaderMessage>(1024);
+        Self {
+            market,
+            ws_url: combined_stream_url(market, &streams),
+            streams,
+            config,
+            next_id: 0,
+            recent: RecentFrames::new(RECENT_FRAMES),
+            agg_trade_gaps: AggTradeGaps::new(market),
+            frame_tx,
+            frame_rx,
+            commands,
+            contract_tx,
+            next_request_id: 0,
+        }
+    }
+
+    async fn connect(&mut self) -> Result<Reader, tungstenite::Error> {
+        self.next_id += 1;
+        let reader = spawn_reader(
+            self.next_id,
+            &self.ws_url,
+            self.config,
+            self.frame_tx.clone(),
+        )
+        .await?;
+        info!(
+            "Connected to Binance {} streams {} (connection {})",
+            self.market,
+            self.streams.join(", "),
+            reader.id
+        );
+        Ok(reader)
+    }
+
+    /// Opens a replacement connection in the background, so frames of the
+    /// active one keep being routed while the handshake runs.
+    fn connect_in_background(&mut self) -> oneshot::Receiver<Result<Reader, tungstenite::Error>> {
+        self.next_id += 1;
+        let (id, url, config) = (self.next_id, self.ws_url.clone(), self.config);
+        let frame_tx = self.frame_tx.clone();
+        let (reader_tx, reader_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            // A reader nobody waits for anymore is dropped, which closes it
+            let _ = reader_tx.send(spawn_reader(id, &url, config, frame_tx).await);
+        });
+        reader_rx
+    }
+
+    async fn run(mut self, tx: mpsc::Sender<BinanceData>) {
+        let mut backoff = Backoff::default();
+        let mut disconnected_at: Option<u64> = None;
+
+        loop {
+            match self.connect().await {
+                Ok(reader) => {
+                    if let Some(disconnected_at) = disconnected_at.take() {
+                        let reconnected_at = chrono::Utc::now().timestamp_millis() as u64;
+                        for (symbol, streams) in streams_by_symbol(&self.streams) {
+                            let event = ReconnectEvent {
+                                symbol,
+                                market: self.market,
+                                streams,
+                                attempt: backoff.attempt(),
+                                disconnected_at,
+                                reconnected_at,
+                            };
+                            if tx.send(BinanceData::Reconnect(event)).await.is_err() {
+                                error!("Failed to send reconnect event");
+                            }
+                        }
+                    }
+
+                    let connected_at = self.run_connection(reader, &tx).await;
+                    if connected_at.elapsed() >= STABLE_CONNECTION {
+                        backoff.reset();
+                    }
                 }
-                s if s.ends_with("@aggTrade") => {
-                    let tx_clone = tx.clone();
-                    tokio::spawn(async move {
-                        handle_agg_trade(read, write, tx_clone).await;
-                    });
+                Err(e) => error!("Failed to connect to Binance streams: {}", e),
+            }
+
+            if tx.is_closed() {
+                info!("Receiver dropped, stopping Binance streams");
+                return;
+            }
+            if self.streams.is_empty() {
+                info!(
+                    "No Binance {} streams left, closing connection",
+                    self.market
+                );
+                return;
+            }
+
+            disconnected_at.get_or_insert(chrono::Utc::now().timestamp_millis() as u64);
+            let delay = backoff.next_delay();
+            warn!(
+                "Binance connection dropped, reconnecting in {:?} (attempt {})",
+                delay,
+                backoff.attempt()
+            );
+            sleep(delay).await;
+        }
+    }
+
+    /// Routes frames until the active connection drops, rotating it when due:
+    /// the replacement connects first and both run for `rotation_overlap`, with
+    /// the frames delivered twice dropped. Returns when the last active
+    /// connection was opened.
+    async fn run_connection(
+        &mut self,
+        mut active: Reader,
+        tx: &mpsc::Sender<BinanceData>,
+    ) -> Instant {
+        let market = self.market;
+
+        // Snapshots are fetched in the background while diff events are buffered
+        let (snapshot_tx, mut snapshot_rx) = mpsc::channel::<(String, DepthSnapShot)>(16);
+        let mut order_books = HashMap::new();
+        for stream in self.streams.iter().filter(|s| is_diff_depth(s)) {
+            let symbol = stream.split('@').next().unwrap_or_default();
+            order_books.insert(stream.clone(), OrderBookState::new(symbol, market));
+            request_depth_snapshot(
+                stream.clone(),
+                symbol.to_string(),
+                market,
+                snapshot_tx.clone(),
+            );
+        }
+
+        let mut draining: Option<Reader> = None;
+        let mut replacement: Option<oneshot::Receiver<Result<Reader, tungstenite::Error>>> = None;
+        let mut rotate_at = active.connected_at + self.config.rotation_interval;
+        let mut drain_until = Instant::now();
+
+        loop {
+            tokio::select! {
+                msg = self.frame_rx.recv() => match msg {
+                    Some(ReaderMessage::Frame(id, text)) => {
+                        let current = id == active.id
+                            || draining.as_ref().is_some_and(|reader| reader.id == id);
+                        if current && self.recent.insert(&text) {
+                            dispatch_frame(
+                                &text,
+                                market,
+                                &mut order_books,
+                                &snapshot_tx,
+                                &mut self.agg_trade_gaps,
+                                self.contract_tx.as_ref(),
+                                tx,
+                            )
+                            .await
+                        }
+                    }
+                    Some(ReaderMessage::Closed(id)) if id != active.id => (),
+                    Some(ReaderMessage::Closed(_)) | None => break,
+                },
+                Some((stream, snapshot)) = snapshot_rx.recv() => {
+                    if let Some(state) = order_books.get_mut(&stream) {
+                        if state.apply_snapshot(snapshot, tx).await == SyncStatus::Resync {
+                            request_depth_snapshot(
+                                stream,
+                                state.symbol().to_string(),
+                                market,
+                                snapshot_tx.clone(),
+                            );
+                        }
+                    }
+                }
+                Ok(StreamCommand::Remove(symbol)) = self.commands.recv() => {
+                    let removed: Vec<String> = self
+                        .streams
+                        .iter()
+                        .filter(|stream| stream_symbol(stream) == symbol)
+                        .cloned()
+                        .collect();
+                    if removed.is_empty() {
+                        continue;
+                    }
+
+                    self.next_request_id += 1;
+                    let request = serde_json::json!({
+                        "method": "UNSUBSCRIBE",
+                        "params": removed,
+                        "id": self.next_request_id,
+                    })
+                    .to_string();
+                    active.send_text(request.clone());
+                    if let Some(old) = draining.as_ref() {
+                        old.send_text(request);
+                    }
+
+                    info!("Unsubscribed from {}", removed.join(", "));
+                    self.streams.retain(|stream| !removed.contains(stream));
+                    for stream in &removed {
+                        order_books.remove(stream);
+                    }
+                    // Reconnects only subscribe to the remaining streams
+                    self.ws_url = combined_stream_url(market, &self.streams);
+                    if self.streams.is_empty() {
+                        break;
+                    }
+                }
+                _ = sleep_until(rotate_at), if draining.is_none() && replacement.is_none() => {
+                    replacement = Some(self.connect_in_background());
+                }
+                result = async { replacement.as_mut().unwrap().await }, if replacement.is_some() => {
+                    replacement = None;
+                    match result {
+                        Ok(Ok(reader)) => {
+                            info!("Rotating Binance connection {} to {}", active.id, reader.id);
+                            rotate_at = reader.connected_at + self.config.rotation_interval;
+                            drain_until = Instant::now() + self.config.rotation_overlap;
+                            draining = Some(std::mem::replace(&mut active, reader));
+                        }
+                        Ok(Err(e)) => {
+                            error!("Failed to open replacement connection: {}", e);
+                            rotate_at = Instant::now() + ROTATION_RETRY;
+                        }
+                        Err(_) => rotate_at = Instant::now() + ROTATION_RETRY,
+                    }
+                }
+                _ = sleep_until(drain_until), if draining.is_some() => {
+                    // Dropping the reader closes its socket
+                    if let Some(old) = draining.take() {
+                        info!("Closing rotated Binance connection {}", old.id);
+                    }
                 }
-                _ => unreachable!(),
             }
         }
-        Ok(())
+        active.connected_at
+    }
+}
+
+fn combined_stream_url(market: BinanceMarket, streams: &[String]) -> String {
+    format!("{}/stream?streams={}", market.ws_base(), streams.join("/"))
+}
+
+/// Routes a combined stream frame to the parser of its stream.
+async fn dispatch_frame(
+    text: &str,
+    market: BinanceMarket,
+    order_books: &mut HashMap<String, OrderBookState>,
+    snapshot_tx: &mpsc::Sender<(String, DepthSnapShot)>,
+    agg_trade_gaps: &mut AggTradeGaps,
+    contract_tx: Option<&mpsc::Sender<ContractInfoEvent>>,
+    tx: &mpsc::Sender<BinanceData>,
+) {
+    let frame = match serde_json::from_str::<CombinedStreamFrame>(text) {
+        Ok(frame) => frame,
+        Err(e) => {
+            error!("Failed to parse event: {} - Error: {}", text, e);
+            return;
+        }
+    };
+
+    let Some(stream) = frame.stream else {
+        match (frame.id, frame.error) {
+            (Some(id), Some(e)) => error!("Binance rejected request {}: {}", id, e),
+            (Some(id), None) => info!("Binance acknowledged request {}", id),
+            _ => warn!("Received frame without stream: {}", text),
+        }
+        return;
+    };
+
+    match stream.as_str() {
+        s if is_diff_depth(s) => match order_books.get_mut(s) {
+            Some(state) => {
+                if state.handle_order_book(text, tx).await == SyncStatus::Resync {
+                    request_depth_snapshot(
+                        s.to_string(),
+                        state.symbol().to_string(),
+                        market,
+                        snapshot_tx.clone(),
+                    );
+                }
+            }
+            None => error!("No order book for stream {}", s),
+        },
+        s if partial_depth_levels(s).is_some() => handle_partial_depth(text, s, market, tx).await,
+        s if s.ends_with("@forceOrder") || s == ALL_MARKET_LIQUIDATIONS => {
+            handle_liquidation_order(text, market, tx).await
+        }
+        s if s.ends_with("@aggTrade") => handle_agg_trade(text, market, agg_trade_gaps, tx).await,
+        s if s.ends_with("@markPrice@1s") => handle_mark_price(text, market, tx).await,
+        s if s.ends_with("@bookTicker") => handle_book_ticker(text, market, tx).await,
+        CONTRACT_INFO_STREAM => handle_contract_info(text, market, contract_tx, tx).await,
+        s if s.contains("@kline_") => handle_kline(text, market, tx).await,
+        s if s.contains("@continuousKline_") => handle_continuous_kline(text, market, tx).await,
+        s => warn!("Received frame for unknown stream {}", s),
     }
 }
@@ -0,0 +1,48 @@
+use std::fmt;
+
+/// Bitget product line a channel is subscribed on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum BitgetInstType {
+    Spot,
+    #[default]
+    UsdtFutures, // USDT margined perpetuals, e.g. "BTCUSDT"
+    CoinFutures, // Coin margined futures, e.g. "BTCUSD"
+    UsdcFutures, // USDC margined perpetuals, e.g. "BTCPERP"
+}
+
+#[allow(dead_code)]
+impl BitgetInstType {
+    /// Value of `instType` in subscriptions and stored rows.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BitgetInstType::Spot => "SPOT",
+            BitgetInstType::UsdtFutures => "USDT-FUTURES",
+            BitgetInstType::CoinFutures => "COIN-FUTURES",
+            BitgetInstType::UsdcFutures => "USDC-FUTURES",
+        }
+    }
+
+    pub fn is_futures(&self) -> bool {
+        !matches!(self, BitgetInstType::Spot)
+    }
+}
+
+impl fmt::Display for BitgetInstType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for BitgetInstType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_uppercase().as_str() {
+            "SPOT" => Ok(BitgetInstType::Spot),
+            "USDT-FUTURES" => Ok(BitgetInstType::UsdtFutures),
+            "COIN-FUTURES" => Ok(BitgetInstType::CoinFutures),
+            "USDC-FUTURES" => Ok(BitgetInstType::UsdcFutures),
+            _ => Err(format!("Unknown Bitget instType {}", s)),
+        }
+    }
+}
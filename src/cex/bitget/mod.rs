@@ -1,2 +1,5 @@
+pub mod inst_type;
 pub mod order_book;
+pub mod ticker;
+pub mod trade;
 pub mod websocket;
@@ -1,6 +1,17 @@
+use std::collections::{BTreeMap, HashMap};
+
+use log::{debug, error, info};
 use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use super::inst_type::BitgetInstType;
+use super::websocket::{BitgetData, BitgetStreamArg};
+use crate::cex::combined_order_book::{OrderBook, PriceKey, Venue, VenueBook};
+
+// Bitget checksums the top 25 levels of each side
+const CHECKSUM_LEVELS: usize = 25;
 
-use super::websocket::BitgetStreamArg;
+pub const DEPTH_CHANNELS: [&str; 4] = ["books", "books1", "books5", "books15"];
 
 #[derive(Debug, Deserialize)]
 #[allow(dead_code)]
@@ -16,6 +27,382 @@ pub struct BitgetDepthMessage {
 pub struct DepthData {
     pub asks: Vec<(String, String)>, // List of asks (price, quantity)
     pub bids: Vec<(String, String)>, // List of bids (price, quantity)
-    pub checksum: i64,               // Checksum for validation
-    pub ts: String,                  // Timestamp as a string
+    // Missing on snapshot-only channels
+    #[serde(default)]
+    pub checksum: i64, // Checksum for validation
+    pub ts: String, // Timestamp as a string
+}
+
+/// Depth of one book as received, a full snapshot or the levels that changed.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct BitgetDepthEvent {
+    pub symbol: String,
+    pub inst_type: String,
+    pub channel: String,
+    pub action: String,              // "snapshot" or "update"
+    pub bids: Vec<(String, String)>, // (price, quantity), quantity "0" removes the level
+    pub asks: Vec<(String, String)>, // (price, quantity), quantity "0" removes the level
+    pub checksum: i64,
+    pub ts: u64, // Unix time in milliseconds
+}
+
+impl BitgetDepthEvent {
+    pub fn is_snapshot(&self) -> bool {
+        self.action == "snapshot"
+    }
+}
+
+/// Sent when a book failed its checksum and is resubscribed for a fresh snapshot.
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct BitgetChecksumEvent {
+    pub symbol: String,
+    pub inst_type: String,
+    pub channel: String,
+    pub failures: u64, // Mismatches of this book since the collector started
+    pub ts: u64,       // Unix time in milliseconds of the mismatching update
+}
+
+/// Outcome of applying a depth event to a book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncStatus {
+    Synced,
+    // Waiting for a snapshot, the event was dropped
+    Pending,
+    // The checksum didn't match, a fresh snapshot is needed
+    Resync,
+}
+
+/// Local copy of one Bitget book, checked against the checksum Bitget sends
+/// with every update. The raw level strings are kept next to the typed book
+/// because the checksum covers them as sent, e.g. "0.5000" and not "0.5".
+/// "books1", "books5" and "books15" only push snapshots, which replace the
+/// book and need no checksum.
+pub struct BitgetBookState {
+    symbol: String,
+    venue: Venue,
+    order_book: OrderBook,
+    bids: BTreeMap<PriceKey, (String, String)>,
+    asks: BTreeMap<PriceKey, (String, String)>,
+    synced: bool,
+    checksum_failures: u64,
+}
+
+impl BitgetBookState {
+    pub fn new(symbol: &str, inst_type: BitgetInstType) -> Self {
+        Self {
+            symbol: symbol.to_uppercase(),
+            venue: Venue::Bitget(inst_type),
+            order_book: OrderBook::new(),
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            synced: false,
+            checksum_failures: 0,
+        }
+    }
+
+    /// Copy of the book for the consolidated view.
+    pub fn venue_book(&self) -> VenueBook {
+        VenueBook {
+            symbol: self.symbol.clone(),
+            venue: self.venue,
+            book: self.order_book.clone(),
+        }
+    }
+
+    /// Checksum mismatches since the collector started, a data quality metric.
+    pub fn checksum_failures(&self) -> u64 {
+        self.checksum_failures
+    }
+
+    pub fn is_synced(&self) -> bool {
+        self.synced
+    }
+
+    /// Drops updates until the next snapshot, e.g. after reconnecting.
+    pub fn reset(&mut self) {
+        self.synced = false;
+    }
+
+    pub fn apply(&mut self, event: &BitgetDepthEvent) -> SyncStatus {
+        if event.is_snapshot() {
+            self.bids.clear();
+            self.asks.clear();
+            self.synced = true;
+        } else if !self.synced {
+            return SyncStatus::Pending;
+        }
+
+        apply_raw_levels(&mut self.bids, &event.bids);
+        apply_raw_levels(&mut self.asks, &event.asks);
+        if event.is_snapshot() {
+            self.order_book
+                .apply_snapshot(event.ts, &event.bids, &event.asks);
+        } else {
+            self.order_book
+                .apply_update(event.ts, &event.bids, &event.asks);
+        }
+
+        if !event.is_snapshot() && self.checksum() != event.checksum as i32 {
+            self.checksum_failures += 1;
+            self.synced = false;
+            error!(
+                "Bitget {} checksum mismatch (failure {}): Resubscribing",
+                event.symbol, self.checksum_failures
+            );
+            return SyncStatus::Resync;
+        }
+        SyncStatus::Synced
+    }
+
+    /// CRC32 of "bid1Price:bid1Size:ask1Price:ask1Size:bid2Price:..." over the
+    /// top 25 levels, as a signed 32 bit integer. A shorter side is skipped
+    /// once it runs out.
+    fn checksum(&self) -> i32 {
+        let mut bids = self.bids.values().rev();
+        let mut asks = self.asks.values();
+        let mut parts: Vec<&str> = Vec::with_capacity(CHECKSUM_LEVELS * 4);
+        for _ in 0..CHECKSUM_LEVELS {
+            if let Some((price, size)) = bids.next() {
+                parts.push(price);
+                parts.push(size);
+            }
+            if let Some((price, size)) = asks.next() {
+                parts.push(price);
+                parts.push(size);
+            }
+        }
+        crc32fast::hash(parts.join(":").as_bytes()) as i32
+    }
+}
+
+fn apply_raw_levels(
+    levels: &mut BTreeMap<PriceKey, (String, String)>,
+    changes: &[(String, String)],
+) {
+    for (price, size) in changes {
+        let Some(price_key) = PriceKey::parse(price) else {
+            continue;
+        };
+        if size.parse::<f64>().is_ok_and(|size| size == 0.0) {
+            levels.remove(&price_key);
+        } else {
+            levels.insert(price_key, (price.clone(), size.clone()));
+        }
+    }
+}
+
+/// Applies a depth message to its book and forwards the events that leave
+/// the book in sync, followed by the book itself with `book_snapshots`.
+/// Returns the stream to resubscribe to when the book needs a fresh snapshot.
+pub async fn handle_depth(
+    text: &str,
+    books: &mut HashMap<BitgetStreamArg, BitgetBookState>,
+    book_snapshots: bool,
+    tx: &mpsc::Sender<BitgetData>,
+) -> Option<BitgetStreamArg> {
+    let message = match serde_json::from_str::<BitgetDepthMessage>(text) {
+        Ok(message) => message,
+        Err(e) => {
+            error!("Failed to parse event: {} - Error: {}", text, e);
+            return None;
+        }
+    };
+
+    // Args are built from a BitgetInstType, so the instType always parses
+    let inst_type = message.arg.instType.parse().unwrap_or_default();
+    let state = books
+        .entry(message.arg.clone())
+        .or_insert_with(|| BitgetBookState::new(&message.arg.instId, inst_type));
+    for data in message.data {
+        let event = BitgetDepthEvent {
+            symbol: message.arg.instId.clone(),
+            inst_type: message.arg.instType.clone(),
+            channel: message.arg.channel.clone(),
+            action: message.action.clone(),
+            bids: data.bids,
+            asks: data.asks,
+            checksum: data.checksum,
+            ts: data.ts.parse().unwrap_or_default(),
+        };
+
+        // Snapshot-only channels push a snapshot every time, only the first one is logged
+        let was_synced = state.is_synced();
+        match state.apply(&event) {
+            SyncStatus::Synced => {
+                if !was_synced {
+                    info!("Applied Bitget depth snapshot for {}", message.arg);
+                }
+                if tx.send(BitgetData::Depth(event)).await.is_err() {
+                    error!("Failed to send Bitget depth event");
+                }
+                if book_snapshots
+                    && tx
+                        .send(BitgetData::OrderBook(state.venue_book()))
+                        .await
+                        .is_err()
+                {
+                    error!("Failed to send Bitget order book update");
+                }
+            }
+            SyncStatus::Pending => {
+                debug!(
+                    "Dropping Bitget depth update for {} until the next snapshot",
+                    message.arg
+                );
+            }
+            SyncStatus::Resync => {
+                let checksum_event = BitgetChecksumEvent {
+                    symbol: event.symbol,
+                    inst_type: event.inst_type,
+                    channel: event.channel,
+                    failures: state.checksum_failures(),
+                    ts: event.ts,
+                };
+                if tx
+                    .send(BitgetData::ChecksumFailure(checksum_event))
+                    .await
+                    .is_err()
+                {
+                    error!("Failed to send Bitget checksum failure");
+                }
+                return Some(message.arg);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cex::combined_order_book::Side;
+
+    fn levels(levels: &[(&str, &str)]) -> Vec<(String, String)> {
+        levels
+            .iter()
+            .map(|(price, size)| (price.to_string(), size.to_string()))
+            .collect()
+    }
+
+    fn event(
+        action: &str,
+        bids: &[(&str, &str)],
+        asks: &[(&str, &str)],
+        checksum: i64,
+    ) -> BitgetDepthEvent {
+        BitgetDepthEvent {
+            symbol: "BTCUSDT".to_string(),
+            inst_type: "USDT-FUTURES".to_string(),
+            channel: "books".to_string(),
+            action: action.to_string(),
+            bids: levels(bids),
+            asks: levels(asks),
+            checksum,
+            ts: 1,
+        }
+    }
+
+    fn synced_book(bids: &[(&str, &str)], asks: &[(&str, &str)]) -> BitgetBookState {
+        let mut state = BitgetBookState::new("BTCUSDT", BitgetInstType::UsdtFutures);
+        assert_eq!(
+            state.apply(&event("snapshot", bids, asks, 0)),
+            SyncStatus::Synced
+        );
+        state
+    }
+
+    #[test]
+    fn checksum_interleaves_bids_and_asks() {
+        // Documented example: "3366.1:7:3366.8:9:3366:6:3368:8"
+        let state = synced_book(
+            &[("3366.1", "7"), ("3366", "6")],
+            &[("3366.8", "9"), ("3368", "8")],
+        );
+        assert_eq!(state.checksum(), -1881014294);
+    }
+
+    #[test]
+    fn checksum_skips_the_shorter_side() {
+        // Documented example: "3366.1:7:3366.8:9:3368:8:3372:8"
+        let state = synced_book(
+            &[("3366.1", "7")],
+            &[("3366.8", "9"), ("3368", "8"), ("3372", "8")],
+        );
+        assert_eq!(state.checksum(), 831078360);
+    }
+
+    #[test]
+    fn checksum_uses_levels_as_sent() {
+        let state = synced_book(
+            &[("3366.1", "7.0000"), ("3366", "6")],
+            &[("3366.8", "9"), ("3368", "8")],
+        );
+        let expected = crc32fast::hash(b"3366.1:7.0000:3366.8:9:3366:6:3368:8") as i32;
+        assert_eq!(state.checksum(), expected);
+    }
+
+    #[test]
+    fn checksum_covers_the_top_25_levels() {
+        let bids: Vec<(String, String)> = (0..30)
+            .map(|i| (format!("{}", 1000 - i), "1".to_string()))
+            .collect();
+        let asks: Vec<(String, String)> = (0..30)
+            .map(|i| (format!("{}", 1001 + i), "2".to_string()))
+            .collect();
+        let mut state = BitgetBookState::new("BTCUSDT", BitgetInstType::UsdtFutures);
+        let snapshot = BitgetDepthEvent {
+            bids,
+            asks,
+            ..event("snapshot", &[], &[], 0)
+        };
+        assert_eq!(state.apply(&snapshot), SyncStatus::Synced);
+
+        let expected = (0..CHECKSUM_LEVELS)
+            .map(|i| format!("{}:1:{}:2", 1000 - i, 1001 + i))
+            .collect::<Vec<_>>()
+            .join(":");
+        assert_eq!(
+            state.checksum(),
+            crc32fast::hash(expected.as_bytes()) as i32
+        );
+    }
+
+    #[test]
+    fn update_with_matching_checksum_stays_synced() {
+        let mut state = synced_book(
+            &[("3366.1", "7"), ("3366", "6"), ("3365", "1")],
+            &[("3366.8", "9"), ("3368", "8")],
+        );
+        // Removing 3365 leaves the documented example
+        let update = event("update", &[("3365", "0")], &[], -1881014294);
+        assert_eq!(state.apply(&update), SyncStatus::Synced);
+        assert_eq!(state.checksum_failures(), 0);
+        assert_eq!(state.venue_book().book.len(Side::Bid), 2);
+    }
+
+    #[test]
+    fn update_with_mismatching_checksum_resyncs() {
+        let mut state = synced_book(&[("3366.1", "7")], &[("3366.8", "9")]);
+        let update = event("update", &[("3366", "6")], &[], 1);
+        assert_eq!(state.apply(&update), SyncStatus::Resync);
+        assert_eq!(state.checksum_failures(), 1);
+        assert!(!state.is_synced());
+
+        // Updates are dropped until the next snapshot
+        let update = event("update", &[("3365", "1")], &[], 0);
+        assert_eq!(state.apply(&update), SyncStatus::Pending);
+        let snapshot = event("snapshot", &[("3366.1", "7")], &[("3366.8", "9")], 0);
+        assert_eq!(state.apply(&snapshot), SyncStatus::Synced);
+        assert_eq!(state.checksum_failures(), 1);
+    }
+
+    #[test]
+    fn book_takes_its_market_from_the_inst_type() {
+        let state = BitgetBookState::new("btcusdt", BitgetInstType::Spot);
+        let venue_book = state.venue_book();
+        assert_eq!(venue_book.symbol, "BTCUSDT");
+        assert_eq!(venue_book.venue, Venue::Bitget(BitgetInstType::Spot));
+    }
 }
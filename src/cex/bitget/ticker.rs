@@ -0,0 +1,83 @@
+use log::error;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use super::websocket::{BitgetData, BitgetStreamArg};
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+pub struct BitgetTickerMessage {
+    pub action: String,
+    pub arg: BitgetStreamArg,
+    pub data: Vec<TickerData>,
+}
+
+/// Futures tickers also carry mark, index and funding; spot tickers don't.
+#[derive(Debug, Deserialize)]
+#[allow(non_snake_case)]
+#[allow(dead_code)]
+pub struct TickerData {
+    pub lastPr: String,                  // Last price
+    pub bidPr: String,                   // Best bid price
+    pub askPr: String,                   // Best ask price
+    pub bidSz: String,                   // Best bid size
+    pub askSz: String,                   // Best ask size
+    pub baseVolume: String,              // 24h volume in base coin
+    pub quoteVolume: String,             // 24h volume in quote coin
+    pub markPrice: Option<String>,       // Mark price, futures only
+    pub indexPrice: Option<String>,      // Index price, futures only
+    pub fundingRate: Option<String>,     // Current funding rate, futures only
+    pub nextFundingTime: Option<String>, // Next settlement time, futures only
+    pub holdingAmount: Option<String>,   // Open interest in base coin, futures only
+    pub ts: String,                      // Timestamp as a string
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct BitgetTickerEvent {
+    pub symbol: String,
+    pub inst_type: String,
+    pub last_price: String,
+    pub bid_price: String,
+    pub ask_price: String,
+    pub bid_size: String,
+    pub ask_size: String,
+    pub base_volume: String,
+    pub quote_volume: String,
+    pub mark_price: Option<String>,
+    pub index_price: Option<String>,
+    pub funding_rate: Option<String>,
+    pub next_funding_time: Option<u64>, // Unix time in milliseconds
+    pub holding_amount: Option<String>,
+    pub ts: u64, // Unix time in milliseconds
+}
+
+pub async fn handle_ticker(text: &str, tx: &mpsc::Sender<BitgetData>) {
+    match serde_json::from_str::<BitgetTickerMessage>(text) {
+        Ok(message) => {
+            for data in message.data {
+                let event = BitgetTickerEvent {
+                    symbol: message.arg.instId.clone(),
+                    inst_type: message.arg.instType.clone(),
+                    last_price: data.lastPr,
+                    bid_price: data.bidPr,
+                    ask_price: data.askPr,
+                    bid_size: data.bidSz,
+                    ask_size: data.askSz,
+                    base_volume: data.baseVolume,
+                    quote_volume: data.quoteVolume,
+                    mark_price: data.markPrice,
+                    index_price: data.indexPrice,
+                    funding_rate: data.fundingRate,
+                    next_funding_time: data.nextFundingTime.and_then(|time| time.parse().ok()),
+                    holding_amount: data.holdingAmount,
+                    ts: data.ts.parse().unwrap_or_default(),
+                };
+                if tx.send(BitgetData::Ticker(event)).await.is_err() {
+                    error!("Failed to send Bitget ticker event");
+                }
+            }
+        }
+        Err(e) => error!("Failed to parse event: {} - Error: {}", text, e),
+    }
+}
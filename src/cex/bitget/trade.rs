@@ -0,0 +1,62 @@
+use log::error;
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use super::websocket::{BitgetData, BitgetStreamArg};
+
+#[derive(Debug, Deserialize)]
+#[allow(dead_code)]
+pub struct BitgetTradeMessage {
+    pub action: String, // "snapshot" with recent trades, then "update"
+    pub arg: BitgetStreamArg,
+    pub data: Vec<TradeData>,
+}
+
+#[derive(Debug, Deserialize)]
+#[allow(non_snake_case)]
+#[allow(dead_code)]
+pub struct TradeData {
+    pub ts: String,      // Trade time as a string
+    pub price: String,   // Price
+    pub size: String,    // Quantity
+    pub side: String,    // Taker side, "buy" or "sell"
+    pub tradeId: String, // Trade ID
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct BitgetTradeEvent {
+    pub symbol: String,
+    pub inst_type: String,
+    pub trade_id: u64,
+    pub price: String,
+    pub size: String,
+    pub side: String, // Taker side, "buy" or "sell"
+    pub ts: u64,      // Unix time in milliseconds
+}
+
+pub async fn handle_trade(text: &str, tx: &mpsc::Sender<BitgetData>) {
+    match serde_json::from_str::<BitgetTradeMessage>(text) {
+        Ok(message) => {
+            for data in message.data {
+                let Ok(trade_id) = data.tradeId.parse::<u64>() else {
+                    error!("Invalid Bitget trade ID {}", data.tradeId);
+                    continue;
+                };
+                let event = BitgetTradeEvent {
+                    symbol: message.arg.instId.clone(),
+                    inst_type: message.arg.instType.clone(),
+                    trade_id,
+                    price: data.price,
+                    size: data.size,
+                    side: data.side,
+                    ts: data.ts.parse().unwrap_or_default(),
+                };
+                if tx.send(BitgetData::Trade(event)).await.is_err() {
+                    error!("Failed to send Bitget trade event");
+                }
+            }
+        }
+        Err(e) => error!("Failed to parse event: {} - Error: {}", text, e),
+    }
+}
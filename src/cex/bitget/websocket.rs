@@ -1,18 +1,66 @@
-use futures::{SinkExt, StreamExt};
-use log::info;
+use std::collections::HashMap;
+use std::fmt;
+
+use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc;
-use tokio_tungstenite::tungstenite::Message;
+use tokio::time::{interval_at, sleep, Duration, Instant};
+
+use crate::cex::backoff::Backoff;
+use crate::cex::bitget::{
+    inst_type::BitgetInstType,
+    order_book::{
+        handle_depth, BitgetBookState, BitgetChecksumEvent, BitgetDepthEvent, DEPTH_CHANNELS,
+    },
+    ticker::{handle_ticker, BitgetTickerEvent},
+    trade::{handle_trade, BitgetTradeEvent},
+};
+use crate::cex::combined_order_book::VenueBook;
+use crate::cex::connection::{
+    spawn_reader, ConnectionConfig, Reader, ReaderMessage, STABLE_CONNECTION,
+};
+
+const BITGET_PUBLIC_WS: &str = "wss://ws.bitget.com/v2/ws/public";
+
+// Bitget closes connections that sent no "ping" for 2 minutes
+const PING_INTERVAL: Duration = Duration::from_secs(30);
+
+// Bitget advises subscribing to fewer than 50 channels per connection
+const MAX_ARGS_PER_CONNECTION: usize = 50;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub enum BitgetData {
+    Depth(BitgetDepthEvent),
+    Trade(BitgetTradeEvent),
+    Ticker(BitgetTickerEvent),
+    OrderBook(VenueBook), // Book after each depth event, see `with_book_snapshots`
+    ChecksumFailure(BitgetChecksumEvent),
+}
 
-pub enum BitgetData {}
+#[allow(dead_code)]
+impl BitgetData {
+    /// Uppercase symbol (e.g. "BTCUSDT") the event belongs to.
+    pub fn symbol(&self) -> &str {
+        match self {
+            BitgetData::Depth(event) => &event.symbol,
+            BitgetData::Trade(event) => &event.symbol,
+            BitgetData::Ticker(event) => &event.symbol,
+            BitgetData::OrderBook(order_book) => &order_book.symbol,
+            BitgetData::ChecksumFailure(event) => &event.symbol,
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub struct BitgetStreamBuilder {
-    symbol: String,
-    streams: Vec<BitgetStreamArg>,
+    symbols: Vec<String>,
+    inst_type: BitgetInstType,
+    channels: Vec<String>,
+    book_snapshots: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[allow(non_snake_case)]
 pub struct BitgetStreamArg {
     pub instType: String, // e.g., "USDT-FUTURES"
@@ -20,64 +68,294 @@ pub struct BitgetStreamArg {
     pub instId: String,   // e.g., "BTCUSDT"
 }
 
+impl fmt::Display for BitgetStreamArg {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}", self.instType, self.channel, self.instId)
+    }
+}
+
 #[allow(dead_code)]
 impl BitgetStreamBuilder {
     pub fn new(symbol: &str) -> Self {
+        Self::with_symbols(&[symbol])
+    }
+
+    /// Collects the same channels for every symbol.
+    pub fn with_symbols<S: AsRef<str>>(symbols: &[S]) -> Self {
         Self {
-            symbol: symbol.to_uppercase().to_string(),
-            streams: Vec::new(),
+            symbols: symbols
+                .iter()
+                .map(|symbol| symbol.as_ref().to_uppercase())
+                .collect(),
+            inst_type: BitgetInstType::default(),
+            channels: Vec::new(),
+            book_snapshots: false,
         }
     }
 
-    pub fn with_depth(mut self) -> Self {
-        let arg = BitgetStreamArg {
-            instType: "USDT-FUTURES".to_string(),
-            channel: "books".to_string(),
-            instId: self.symbol.clone(),
-        };
-        self.streams.push(arg);
+    /// Selects the product line every channel is subscribed on (USDT-FUTURES by default).
+    pub fn with_inst_type(mut self, inst_type: BitgetInstType) -> Self {
+        self.inst_type = inst_type;
+        self
+    }
+
+    /// Full depth, kept in sync with incremental updates and checksums.
+    pub fn with_depth(self) -> Self {
+        self.with_channel("books")
+    }
+
+    /// Depth of `channel`: "books" for the full book, or "books1", "books5" and
+    /// "books15" for the top levels, which always push snapshots.
+    pub fn with_depth_channel(self, channel: &str) -> Self {
+        if !DEPTH_CHANNELS.contains(&channel) {
+            error!("Unsupported Bitget depth channel {}", channel);
+            return self;
+        }
+        self.with_channel(channel)
+    }
+
+    /// Also sends the local book after every depth event as `BitgetData::OrderBook`,
+    /// e.g. for the consolidated view.
+    pub fn with_book_snapshots(mut self) -> Self {
+        self.book_snapshots = true;
+        self
+    }
+
+    /// Public trades; the first message after subscribing replays recent trades.
+    pub fn with_trades(self) -> Self {
+        self.with_channel("trade")
+    }
+
+    /// Last price, best bid/ask and 24h volume; futures tickers add mark and
+    /// index price, funding rate and open interest.
+    pub fn with_ticker(self) -> Self {
+        self.with_channel("ticker")
+    }
 
+    fn with_channel(mut self, channel: &str) -> Self {
+        if !self.channels.iter().any(|c| c == channel) {
+            self.channels.push(channel.to_string());
+        }
         self
     }
 
-    #[allow(unused_variables)]
     pub async fn build(
         self,
         tx: mpsc::Sender<BitgetData>,
     ) -> Result<(), Box<dyn std::error::Error>> {
+        // The instType is resolved here so it applies whatever the call order
+        let streams: Vec<BitgetStreamArg> = self
+            .channels
+            .iter()
+            .flat_map(|channel| {
+                self.symbols.iter().map(|symbol| BitgetStreamArg {
+                    instType: self.inst_type.as_str().to_string(),
+                    channel: channel.clone(),
+                    instId: symbol.clone(),
+                })
+            })
+            .collect();
+
+        let connections = streams.chunks(MAX_ARGS_PER_CONNECTION);
         info!(
-            "Starting {} Bitget streams for {}",
-            self.streams.len(),
-            self.symbol
+            "Starting {} Bitget {} streams for {} over {} connection(s)",
+            streams.len(),
+            self.inst_type,
+            self.symbols.join(", "),
+            connections.len()
         );
 
-        for stream in &self.streams {
-            let ws_url = "wss://ws.bitget.com/v2/ws/public";
-            let (ws_stream, _) = tokio_tungstenite::connect_async(ws_url).await?;
-            let (mut write, read) = ws_stream.split();
-
-            // Send the subscription message (stream)
-            let subscription_message = serde_json::json!({
-                "op": "subscribe",
-                "args": [stream]
-            })
-            .to_string();
-
-            write
-                .send(Message::Text(subscription_message.into()))
-                .await?;
-
-            // match stream.channel.as_str() {
-            //     "books" => {
-            //         let symbol = self.symbol.clone();
-            //         let tx_clone = tx.clone();
-            //         tokio::spawn(async move {
-            //             handle_order_book(read, write, &mut)
-            //         })
-            //     }
-            //     _ => unreachable!(),
-            // }
+        for args in connections {
+            let args = args.to_vec();
+            let book_snapshots = self.book_snapshots;
+            let tx_clone = tx.clone();
+            tokio::spawn(async move {
+                BitgetConnection::new(args, book_snapshots)
+                    .run(tx_clone)
+                    .await;
+            });
         }
         Ok(())
     }
 }
+
+/// Keeps a Bitget connection subscribed to `args`: subscribes after every
+/// connect, pings with text "ping" and reconnects with jittered backoff.
+struct BitgetConnection {
+    args: Vec<BitgetStreamArg>,
+    config: ConnectionConfig,
+    next_id: u64,
+    // Outlive connections, so checksum failures are counted over the whole run
+    books: HashMap<BitgetStreamArg, BitgetBookState>,
+    book_snapshots: bool,
+    frame_tx: mpsc::Sender<ReaderMessage>,
+    frame_rx: mpsc::Receiver<ReaderMessage>,
+}
+
+impl BitgetConnection {
+    fn new(args: Vec<BitgetStreamArg>, book_snapshots: bool) -> Self {
+        let (frame_tx, frame_rx) = mpsc::channel::<ReaderMessage>(1024);
+        Self {
+            args,
+            // Bitget expects its text "ping" instead, sent by `run_connection`
+            config: ConnectionConfig {
+                ping_interval: None,
+                ..ConnectionConfig::default()
+            },
+            next_id: 0,
+            books: HashMap::new(),
+            book_snapshots,
+            frame_tx,
+            frame_rx,
+        }
+    }
+
+    async fn connect(&mut self) -> Result<Reader, tokio_tungstenite::tungstenite::Error> {
+        self.next_id += 1;
+        let reader = spawn_reader(
+            self.next_id,
+            BITGET_PUBLIC_WS,
+            self.config,
+            self.frame_tx.clone(),
+        )
+        .await?;
+
+        // Every book starts over from the snapshot sent after subscribing
+        for book in self.books.values_mut() {
+            book.reset();
+        }
+        reader.send_text(subscription_request("subscribe", &self.args));
+
+        info!(
+            "Connected to Bitget streams {} (connection {})",
+            self.args
+                .iter()
+                .map(|arg| arg.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+            reader.id
+        );
+        Ok(reader)
+    }
+
+    async fn run(mut self, tx: mpsc::Sender<BitgetData>) {
+        let mut backoff = Backoff::default();
+
+        loop {
+            match self.connect().await {
+                Ok(reader) => {
+                    self.run_connection(&reader, &tx).await;
+                    if reader.connected_at.elapsed() >= STABLE_CONNECTION {
+                        backoff.reset();
+                    }
+                }
+                Err(e) => error!("Failed to connect to Bitget streams: {}", e),
+            }
+
+            if tx.is_closed() {
+                info!("Receiver dropped, stopping Bitget streams");
+                return;
+            }
+
+            let delay = backoff.next_delay();
+            warn!(
+                "Bitget connection dropped, reconnecting in {:?} (attempt {})",
+                delay,
+                backoff.attempt()
+            );
+            sleep(delay).await;
+        }
+    }
+
+    /// Routes frames until the connection drops.
+    async fn run_connection(&mut self, reader: &Reader, tx: &mpsc::Sender<BitgetData>) {
+        let mut ping = interval_at(Instant::now() + PING_INTERVAL, PING_INTERVAL);
+
+        loop {
+            tokio::select! {
+                msg = self.frame_rx.recv() => match msg {
+                    Some(ReaderMessage::Frame(id, text)) => {
+                        if id != reader.id {
+                            continue;
+                        }
+                        if let Some(arg) = dispatch_frame(&text, &mut self.books, self.book_snapshots, tx).await {
+                            // Bitget sends a fresh snapshot when subscribing again
+                            let args = [arg];
+                            reader.send_text(subscription_request("unsubscribe", &args));
+                            reader.send_text(subscription_request("subscribe", &args));
+                        }
+                    }
+                    Some(ReaderMessage::Closed(id)) if id != reader.id => (),
+                    Some(ReaderMessage::Closed(_)) | None => break,
+                },
+                _ = ping.tick() => reader.send_text("ping".to_string()),
+            }
+        }
+    }
+}
+
+fn subscription_request(op: &str, args: &[BitgetStreamArg]) -> String {
+    serde_json::json!({
+        "op": op,
+        "args": args,
+    })
+    .to_string()
+}
+
+/// Every Bitget frame: an event such as a subscription ack or error, or data of a channel.
+#[derive(Debug, Deserialize)]
+struct BitgetFrame {
+    event: Option<String>,
+    arg: Option<BitgetStreamArg>,
+    code: Option<serde_json::Value>,
+    msg: Option<String>,
+}
+
+/// Routes a Bitget frame to the parser of its channel. Returns the stream to
+/// resubscribe to when its book fell out of sync.
+async fn dispatch_frame(
+    text: &str,
+    books: &mut HashMap<BitgetStreamArg, BitgetBookState>,
+    book_snapshots: bool,
+    tx: &mpsc::Sender<BitgetData>,
+) -> Option<BitgetStreamArg> {
+    if text == "pong" {
+        debug!("Received pong");
+        return None;
+    }
+
+    let frame = match serde_json::from_str::<BitgetFrame>(text) {
+        Ok(frame) => frame,
+        Err(e) => {
+            error!("Failed to parse event: {} - Error: {}", text, e);
+            return None;
+        }
+    };
+
+    let arg = frame
+        .arg
+        .as_ref()
+        .map(|arg| arg.to_string())
+        .unwrap_or_default();
+    match (frame.event.as_deref(), frame.arg.as_ref()) {
+        (Some("subscribe"), _) => info!("Subscribed to Bitget {}", arg),
+        (Some("unsubscribe"), _) => info!("Unsubscribed from Bitget {}", arg),
+        (Some("error"), _) => error!(
+            "Bitget rejected {}: {} {}",
+            arg,
+            frame.code.unwrap_or_default(),
+            frame.msg.unwrap_or_default()
+        ),
+        (Some(event), _) => warn!("Received unknown Bitget event {}: {}", event, text),
+        (None, Some(stream)) => match stream.channel.as_str() {
+            c if c.starts_with("books") => {
+                return handle_depth(text, books, book_snapshots, tx).await
+            }
+            "trade" => handle_trade(text, tx).await,
+            "ticker" => handle_ticker(text, tx).await,
+            c => warn!("Received frame for unknown Bitget channel {}", c),
+        },
+        (None, None) => warn!("Received frame without channel: {}", text),
+    }
+    None
+}
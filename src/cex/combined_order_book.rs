@@ -1,49 +1,489 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 
-use crate::cex::binance::order_book::DepthEvent as BinanceDepthEvent;
+use log::error;
+use tokio::time::{Duration, Instant};
 
+use crate::cex::binance::market::BinanceMarket;
+use crate::cex::bitget::inst_type::BitgetInstType;
+
+// A venue whose book lags the latest update by this long is left out of the consolidated view
+const DEFAULT_STALE_AFTER: Duration = Duration::from_secs(10);
+
+// Prices are kept as integers of 1e-8, the finest tick any venue quotes
+const PRICE_DECIMALS: usize = 8;
+const PRICE_SCALE: i64 = 100_000_000;
+
+/// Fixed-point price, so "100.10" and "100.1" are the same level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PriceKey(i64);
+
+#[allow(dead_code)]
+impl PriceKey {
+    /// Parses a decimal string such as "100.10" without going through a float.
+    /// Returns `None` for malformed prices or prices finer than 1e-8.
+    pub fn parse(price: &str) -> Option<Self> {
+        let (integer, fraction) = price.split_once('.').unwrap_or((price, ""));
+        let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if (integer.is_empty() && fraction.is_empty())
+            || !is_digits(integer)
+            || !is_digits(fraction)
+        {
+            return None;
+        }
+        if fraction.len() > PRICE_DECIMALS && fraction[PRICE_DECIMALS..].bytes().any(|b| b != b'0')
+        {
+            return None;
+        }
+
+        let integer = if integer.is_empty() {
+            0
+        } else {
+            integer.parse::<i64>().ok()?
+        };
+        let fraction = fraction
+            .bytes()
+            .chain(std::iter::repeat(b'0'))
+            .take(PRICE_DECIMALS)
+            .fold(0i64, |value, digit| value * 10 + (digit - b'0') as i64);
+        integer
+            .checked_mul(PRICE_SCALE)?
+            .checked_add(fraction)
+            .map(PriceKey)
+    }
+
+    /// Nearest key to `price`.
+    pub fn from_f64(price: f64) -> Self {
+        Self((price * PRICE_SCALE as f64).round() as i64)
+    }
+
+    pub fn as_f64(self) -> f64 {
+        self.0 as f64 / PRICE_SCALE as f64
+    }
+}
+
+impl fmt::Display for PriceKey {
+    /// Shortest decimal form, e.g. "100.1".
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let integer = self.0 / PRICE_SCALE;
+        let fraction = self.0 % PRICE_SCALE;
+        if fraction == 0 {
+            write!(f, "{}", integer)
+        } else {
+            let fraction = format!("{:0width$}", fraction, width = PRICE_DECIMALS);
+            write!(f, "{}.{}", integer, fraction.trim_end_matches('0'))
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    Bid,
+    Ask,
+}
+
+#[allow(dead_code)]
+impl Side {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Side::Bid => "bid",
+            Side::Ask => "ask",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Level {
+    pub price: PriceKey,
+    pub quantity: f64,
+}
+
+/// Exchange and market a book comes from, e.g. Binance USD-M or Bitget spot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Venue {
+    Binance(BinanceMarket),
+    Bitget(BitgetInstType),
+}
+
+#[allow(dead_code)]
+impl Venue {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Venue::Binance(_) => "binance",
+            Venue::Bitget(_) => "bitget",
+        }
+    }
+
+    pub fn binance_market(&self) -> Option<BinanceMarket> {
+        match self {
+            Venue::Binance(market) => Some(*market),
+            Venue::Bitget(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Venue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Venue::Binance(market) => write!(f, "{} {}", self.as_str(), market),
+            Venue::Bitget(inst_type) => write!(f, "{} {}", self.as_str(), inst_type),
+        }
+    }
+}
+
+/// Sorted levels of a single book.
+#[derive(Debug, Clone)]
+pub struct OrderBook {
+    bids: BTreeMap<PriceKey, f64>, // Price -> Quantity
+    asks: BTreeMap<PriceKey, f64>, // Price -> Quantity
+    pub time: u64,                 // Exchange time of the last update
+    received_at: Instant,          // Local time of the last update
+}
+
+#[allow(dead_code)]
+impl OrderBook {
+    pub fn new() -> Self {
+        Self {
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            time: 0u64,
+            received_at: Instant::now(),
+        }
+    }
+
+    /// Sets the quantity of a level from the strings venues send.
+    /// A zero quantity, whichever way it's written, removes the level.
+    pub fn apply(&mut self, side: Side, price: &str, quantity: &str) {
+        let (Some(price_key), Ok(quantity_value)) =
+            (PriceKey::parse(price), quantity.parse::<f64>())
+        else {
+            error!(
+                "Invalid {} level: price={}, quantity={}",
+                side.as_str(),
+                price,
+                quantity
+            );
+            return;
+        };
+
+        let levels = match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        };
+        if quantity_value == 0.0 {
+            levels.remove(&price_key);
+        } else {
+            levels.insert(price_key, quantity_value);
+        }
+    }
+
+    pub fn apply_levels(&mut self, side: Side, levels: &[(String, String)]) {
+        for (price, quantity) in levels {
+            self.apply(side, price, quantity);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+    }
+
+    /// Replaces the book with a full snapshot.
+    pub fn apply_snapshot(
+        &mut self,
+        time: u64,
+        bids: &[(String, String)],
+        asks: &[(String, String)],
+    ) {
+        self.clear();
+        self.apply_update(time, bids, asks);
+    }
+
+    /// Applies changed levels, a zero quantity removes the level.
+    pub fn apply_update(
+        &mut self,
+        time: u64,
+        bids: &[(String, String)],
+        asks: &[(String, String)],
+    ) {
+        self.apply_levels(Side::Bid, bids);
+        self.apply_levels(Side::Ask, asks);
+        self.touch(time);
+    }
+
+    /// Records that the book was updated at exchange `time`. The local time is
+    /// taken here, where the update is applied, so consumers further down a
+    /// channel judge staleness by when the book changed and not when they read it.
+    pub fn touch(&mut self, time: u64) {
+        self.time = time;
+        self.received_at = Instant::now();
+    }
+
+    /// How long ago the book last changed.
+    pub fn age(&self) -> Duration {
+        self.received_at.elapsed()
+    }
+
+    pub fn best_bid(&self) -> Option<Level> {
+        self.levels(Side::Bid).next()
+    }
+
+    pub fn best_ask(&self) -> Option<Level> {
+        self.levels(Side::Ask).next()
+    }
+
+    pub fn mid_price(&self) -> Option<f64> {
+        let bid = self.best_bid()?;
+        let ask = self.best_ask()?;
+        Some((bid.price.as_f64() + ask.price.as_f64()) / 2.0)
+    }
+
+    /// Levels of `side` from the best price outwards.
+    pub fn levels(&self, side: Side) -> Box<dyn Iterator<Item = Level> + '_> {
+        let to_level = |(price, quantity): (&PriceKey, &f64)| Level {
+            price: *price,
+            quantity: *quantity,
+        };
+        match side {
+            Side::Bid => Box::new(self.bids.iter().rev().map(to_level)),
+            Side::Ask => Box::new(self.asks.iter().map(to_level)),
+        }
+    }
+
+    pub fn top_n(&self, side: Side, n: usize) -> Vec<Level> {
+        self.levels(side).take(n).collect()
+    }
+
+    pub fn len(&self, side: Side) -> usize {
+        match side {
+            Side::Bid => self.bids.len(),
+            Side::Ask => self.asks.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bids.is_empty() && self.asks.is_empty()
+    }
+
+    /// Bid and ask quantity resting within `bps` basis points of the mid price.
+    pub fn depth_within(&self, bps: f64) -> Option<(f64, f64)> {
+        let mid = self.mid_price()?;
+        let low = PriceKey::from_f64(mid * (1.0 - bps / 10_000.0));
+        let high = PriceKey::from_f64(mid * (1.0 + bps / 10_000.0));
+        let bid_quantity = self.bids.range(low..).map(|(_, quantity)| quantity).sum();
+        let ask_quantity = self.asks.range(..=high).map(|(_, quantity)| quantity).sum();
+        Some((bid_quantity, ask_quantity))
+    }
+
+    /// Price -> quantity strings of `side`, for consumers that take raw maps.
+    pub fn to_string_map(&self, side: Side) -> HashMap<String, String> {
+        self.levels(side)
+            .map(|level| (level.price.to_string(), level.quantity.to_string()))
+            .collect()
+    }
+}
+
+impl Default for OrderBook {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Quantity summed over the venues of the consolidated view.
+#[derive(Debug, Clone)]
+pub struct ConsolidatedBook {
+    pub book: OrderBook,
+    pub venues: Vec<Venue>, // Venues that contributed, stale ones are left out
+    attribution: HashMap<(Side, PriceKey), Vec<(Venue, f64)>>,
+}
+
+#[allow(dead_code)]
+impl ConsolidatedBook {
+    /// Quantity each venue quotes at `price`.
+    pub fn attribution(&self, side: Side, price: PriceKey) -> &[(Venue, f64)] {
+        self.attribution
+            .get(&(side, price))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+}
+
+/// Book of one venue as it stood after an update, sent by the venue streams.
+#[derive(Debug, Clone)]
+pub struct VenueBook {
+    pub symbol: String, // Uppercase symbol, e.g. "BTCUSDT"
+    pub venue: Venue,
+    pub book: OrderBook,
+}
+
+/// Latest book of each venue for a symbol, and a consolidated view over them.
+#[allow(dead_code)]
 #[derive(Debug, Clone)]
 pub struct CombinedOrderBook {
-    pub bids: HashMap<String, String>, // Price -> Quantity
-    pub asks: HashMap<String, String>, // Price -> Quantity
-    pub time: u64,
+    pub symbol: String,
+    books: BTreeMap<Venue, OrderBook>,
+    stale_after: Duration,
+    updated_at: Option<Instant>, // Local time of the newest book inserted
+    pub time: u64,               // Exchange time of the last update of any venue
 }
 
 #[allow(dead_code)]
 impl CombinedOrderBook {
-    pub fn new() -> Self {
+    pub fn new(symbol: &str) -> Self {
         Self {
-            bids: HashMap::new(),
-            asks: HashMap::new(),
+            symbol: symbol.to_uppercase(),
+            books: BTreeMap::new(),
+            stale_after: DEFAULT_STALE_AFTER,
+            updated_at: None,
             time: 0u64,
         }
     }
 
-    pub fn update_binance(&mut self, event: &BinanceDepthEvent) {
-        self.time = event.E;
+    /// Leaves a venue out of the consolidated view once its book lags the
+    /// newest book by `stale_after`. Quiet but healthy feeds need a longer duration.
+    pub fn with_stale_after(mut self, stale_after: Duration) -> Self {
+        self.stale_after = stale_after;
+        self
+    }
+
+    pub fn book(&self, venue: Venue) -> Option<&OrderBook> {
+        self.books.get(&venue)
+    }
+
+    pub fn venues(&self) -> impl Iterator<Item = Venue> + '_ {
+        self.books.keys().copied()
+    }
+
+    /// Levels of `side` in the book of `venue`, best first.
+    pub fn levels(&self, venue: Venue, side: Side) -> Vec<Level> {
+        self.book(venue)
+            .map(|book| book.levels(side).collect())
+            .unwrap_or_default()
+    }
+
+    /// Replaces the book of a venue with the one it sent last. Venues arrive
+    /// over separate channels, so an older book may be inserted after a newer one.
+    pub fn insert(&mut self, venue_book: VenueBook) {
+        let received_at = venue_book.book.received_at;
+        self.updated_at = Some(
+            self.updated_at
+                .map_or(received_at, |at| at.max(received_at)),
+        );
+        self.time = self.time.max(venue_book.book.time);
+        self.books.insert(venue_book.venue, venue_book.book);
+    }
+
+    /// Staleness is measured against the newest book rather than the
+    /// clock, so a backlog in front of the consumer doesn't drop healthy venues.
+    pub fn is_stale(&self, venue: Venue) -> bool {
+        self.book(venue).is_none_or(|book| self.lags(book))
+    }
+
+    fn lags(&self, book: &OrderBook) -> bool {
+        self.updated_at.is_some_and(|updated_at| {
+            updated_at.saturating_duration_since(book.received_at) > self.stale_after
+        })
+    }
+
+    /// Sums the quantity per price over every venue that isn't stale.
+    pub fn consolidated(&self) -> ConsolidatedBook {
+        let mut consolidated = ConsolidatedBook {
+            book: OrderBook::new(),
+            venues: Vec::new(),
+            attribution: HashMap::new(),
+        };
 
-        for (price, quantity) in event.b.iter() {
-            if quantity == "0" {
-                self.bids.remove(price);
-            } else {
-                self.bids.insert(price.clone(), quantity.clone());
+        for (venue, book) in &self.books {
+            if self.lags(book) {
+                continue;
             }
-        }
+            consolidated.venues.push(*venue);
+            consolidated.book.time = consolidated.book.time.max(book.time);
 
-        for (price, quantity) in event.a.iter() {
-            if quantity == "0" {
-                self.asks.remove(price);
-            } else {
-                self.asks.insert(price.clone(), quantity.clone());
+            for side in [Side::Bid, Side::Ask] {
+                let levels = match side {
+                    Side::Bid => &mut consolidated.book.bids,
+                    Side::Ask => &mut consolidated.book.asks,
+                };
+                for level in book.levels(side) {
+                    *levels.entry(level.price).or_default() += level.quantity;
+                    consolidated
+                        .attribution
+                        .entry((side, level.price))
+                        .or_default()
+                        .push((*venue, level.quantity));
+                }
             }
         }
+        consolidated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn venue_book(venue: Venue, received_at: Instant) -> VenueBook {
+        let mut book = OrderBook::new();
+        book.apply_snapshot(
+            1,
+            &[("100.0".to_string(), "1.0".to_string())],
+            &[("101.0".to_string(), "2.0".to_string())],
+        );
+        book.received_at = received_at;
+        VenueBook {
+            symbol: "BTCUSDT".to_string(),
+            venue,
+            book,
+        }
+    }
+
+    #[test]
+    fn stale_venue_is_left_out() {
+        let binance = Venue::Binance(BinanceMarket::UsdM);
+        let bitget = Venue::Bitget(BitgetInstType::UsdtFutures);
+        let start = Instant::now();
+        let mut combined = CombinedOrderBook::new("btcusdt");
+        combined.insert(venue_book(bitget, start));
+        combined.insert(venue_book(binance, start + DEFAULT_STALE_AFTER * 2));
+
+        assert!(combined.is_stale(bitget));
+        assert!(!combined.is_stale(binance));
+        let consolidated = combined.consolidated();
+        assert_eq!(consolidated.venues, vec![binance]);
+        assert_eq!(consolidated.book.best_ask().unwrap().quantity, 2.0);
     }
 
-    pub fn update_bitget(&mut self) {
-        // TODO: Implement Bitget order book update
+    #[test]
+    fn older_book_inserted_late_does_not_turn_back_the_clock() {
+        let binance = Venue::Binance(BinanceMarket::UsdM);
+        let bitget = Venue::Bitget(BitgetInstType::UsdtFutures);
+        let start = Instant::now();
+        let mut combined = CombinedOrderBook::new("btcusdt");
+        combined.insert(venue_book(binance, start + DEFAULT_STALE_AFTER * 2));
+        combined.insert(venue_book(bitget, start));
+
+        assert!(combined.is_stale(bitget));
+        assert!(!combined.is_stale(binance));
+        assert_eq!(combined.consolidated().venues, vec![binance]);
     }
 
-    pub fn update_okx(&mut self) {
-        // TODO: Implement Okx order book update
+    #[test]
+    fn fresh_venues_are_summed() {
+        let binance = Venue::Binance(BinanceMarket::UsdM);
+        let bitget = Venue::Bitget(BitgetInstType::UsdtFutures);
+        let start = Instant::now();
+        let mut combined = CombinedOrderBook::new("btcusdt");
+        combined.insert(venue_book(bitget, start));
+        combined.insert(venue_book(binance, start + DEFAULT_STALE_AFTER / 2));
+
+        let consolidated = combined.consolidated();
+        assert_eq!(consolidated.venues, vec![binance, bitget]);
+        assert_eq!(consolidated.book.best_ask().unwrap().quantity, 4.0);
+        let ask = PriceKey::parse("101").unwrap();
+        assert_eq!(
+            consolidated.attribution(Side::Ask, ask),
+            &[(binance, 2.0), (bitget, 2.0)]
+        );
     }
 }
@@ -0,0 +1,220 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+
+use futures::{SinkExt, StreamExt};
+use log::{debug, error, info, warn};
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::{interval_at, sleep_until, timeout, Duration, Instant, MissedTickBehavior};
+use tokio_tungstenite::tungstenite::{self, Message};
+
+// A connection that stayed up this long is considered healthy again
+pub(crate) const STABLE_CONNECTION: Duration = Duration::from_secs(60);
+
+// Give up on a handshake that hasn't completed after this long
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// A supervisor that takes no frame for this long is treated like a stalled connection
+const FRAME_SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Health checks applied to every websocket connection.
+#[derive(Debug, Clone, Copy)]
+pub struct ConnectionConfig {
+    // Reconnect when no data arrived for this long, None for streams that can stay quiet
+    pub stall_timeout: Option<Duration>,
+    // How often the client sends protocol pings, None for venues with their own keepalive
+    pub ping_interval: Option<Duration>,
+    // Open a replacement connection after this long, ahead of the server's cutoff
+    pub rotation_interval: Duration,
+    // How long the old and the new connection run side by side when rotating
+    pub rotation_overlap: Duration,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        Self {
+            stall_timeout: Some(Duration::from_secs(60)),
+            ping_interval: Some(Duration::from_secs(20)),
+            // Binance drops every connection after 24 hours
+            rotation_interval: Duration::from_secs(23 * 60 * 60),
+            rotation_overlap: Duration::from_secs(5),
+        }
+    }
+}
+
+/// What a reader task reports to its supervisor. Messages of a replaced
+/// connection can still arrive after its successor's, so supervisors skip
+/// the IDs that aren't current.
+#[derive(Debug)]
+pub enum ReaderMessage {
+    Frame(u64, String), // Connection ID and text payload
+    Closed(u64),
+}
+
+/// Handle of a running reader task. Dropping it closes the connection.
+pub struct Reader {
+    pub id: u64,
+    pub connected_at: Instant,
+    outgoing: mpsc::UnboundedSender<String>,
+    _stop: oneshot::Sender<()>,
+}
+
+impl Reader {
+    /// Sends a text frame, e.g. a subscription request, over the connection.
+    pub fn send_text(&self, text: String) {
+        if self.outgoing.send(text).is_err() {
+            error!(
+                "Connection {} is closed, dropping outgoing message",
+                self.id
+            );
+        }
+    }
+}
+
+/// Connects to `url` and spawns a task that forwards every text frame to
+/// `frame_tx`, answers server pings, pings the server itself and gives up when
+/// the connection stalls. A supervisor that stops taking frames closes the
+/// connection too, so the loss is reported like any other reconnect instead
+/// of frames going missing. `ReaderMessage::Closed` is sent once the task ends.
+pub async fn spawn_reader(
+    id: u64,
+    url: &str,
+    config: ConnectionConfig,
+    frame_tx: mpsc::Sender<ReaderMessage>,
+) -> Result<Reader, tungstenite::Error> {
+    let (ws_stream, _) = match timeout(CONNECT_TIMEOUT, tokio_tungstenite::connect_async(url)).await
+    {
+        Ok(result) => result?,
+        Err(_) => {
+            let message = format!("no handshake within {:?}", CONNECT_TIMEOUT);
+            return Err(tungstenite::Error::Io(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                message,
+            )));
+        }
+    };
+    let (stop_tx, mut stop_rx) = oneshot::channel::<()>();
+    let (outgoing_tx, mut outgoing_rx) = mpsc::unbounded_channel::<String>();
+
+    tokio::spawn(async move {
+        let (mut write, mut read) = ws_stream.split();
+        let mut ping = config.ping_interval.map(|period| {
+            let mut ping = interval_at(Instant::now() + period, period);
+            ping.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            ping
+        });
+        let mut last_data = Instant::now();
+
+        loop {
+            tokio::select! {
+                msg = read.next() => {
+                    let Some(msg) = msg else { break };
+                    match msg {
+                        Ok(Message::Text(text)) => {
+                            // Bitget answers its text "ping" with "pong", which is no data either
+                            if text.as_str() != "pong" {
+                                last_data = Instant::now();
+                            }
+                            let frame = ReaderMessage::Frame(id, text.to_string());
+                            match timeout(FRAME_SEND_TIMEOUT, frame_tx.send(frame)).await {
+                                Ok(Ok(())) => (),
+                                Ok(Err(_)) => break,
+                                Err(_) => {
+                                    warn!(
+                                        "Connection {} could not hand over a frame for {:?}, forcing reconnect",
+                                        id, FRAME_SEND_TIMEOUT
+                                    );
+                                    break;
+                                }
+                            }
+                        }
+                        Ok(Message::Ping(payload)) => {
+                            if let Err(e) = write.send(Message::Pong(payload)).await {
+                                error!("Failed to send Pong: {}", e);
+                            }
+                        }
+                        Ok(Message::Pong(_)) => debug!("Received Pong"),
+                        Ok(Message::Close(reason)) => {
+                            info!("WebSocket closed: {:?}", reason);
+                            break;
+                        }
+                        Err(e) => {
+                            error!("Error reading message: {}", e);
+                            break;
+                        }
+                        _ => (),
+                    }
+                }
+                Some(text) = outgoing_rx.recv() => {
+                    if let Err(e) = write.send(Message::Text(text.into())).await {
+                        error!("Failed to send message: {}", e);
+                    }
+                }
+                _ = async { ping.as_mut().unwrap().tick().await }, if ping.is_some() => {
+                    if let Err(e) = write.send(Message::Ping(Vec::new().into())).await {
+                        error!("Failed to send Ping, closing connection {}: {}", id, e);
+                        break;
+                    }
+                }
+                _ = sleep_until(last_data + config.stall_timeout.unwrap_or_default()),
+                    if config.stall_timeout.is_some() =>
+                {
+                    warn!(
+                        "No data for {:?} on connection {}, forcing reconnect",
+                        config.stall_timeout.unwrap_or_default(), id
+                    );
+                    break;
+                }
+                _ = &mut stop_rx => {
+                    let _ = write.send(Message::Close(None)).await;
+                    break;
+                }
+            }
+        }
+
+        let _ = frame_tx.send(ReaderMessage::Closed(id)).await;
+    });
+
+    Ok(Reader {
+        id,
+        connected_at: Instant::now(),
+        outgoing: outgoing_tx,
+        _stop: stop_tx,
+    })
+}
+
+/// Bounded set of recently seen payloads, used to drop the frames both
+/// connections deliver while a rotation overlaps them.
+pub struct RecentFrames {
+    capacity: usize,
+    seen: HashSet<u64>,
+    order: VecDeque<u64>,
+}
+
+impl RecentFrames {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            seen: HashSet::with_capacity(capacity),
+            order: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Records `text` and returns false if it was seen recently.
+    pub fn insert(&mut self, text: &str) -> bool {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        if !self.seen.insert(hash) {
+            return false;
+        }
+        if self.order.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.order.push_back(hash);
+        true
+    }
+}
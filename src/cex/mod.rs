@@ -2,4 +2,6 @@ pub mod binance;
 pub mod bitget;
 pub mod okx;
 
+pub mod backoff;
 pub mod combined_order_book;
+pub mod connection;
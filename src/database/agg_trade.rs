@@ -1,6 +1,8 @@
+use std::collections::HashMap;
+
 use tokio_postgres::Client;
 
-use crate::cex::binance::agg_trade::AggregateTradeEvent;
+use crate::cex::binance::{agg_trade::AggregateTradeEvent, market::BinanceMarket};
 
 #[allow(dead_code)]
 pub async fn insert_agg_trade(
@@ -11,10 +13,10 @@ pub async fn insert_agg_trade(
         .execute(
             "INSERT INTO binance.agg_trades (
                 event_time, symbol, aggregate_trade_id, price, quantity, first_trade_id, 
-                last_trade_id, trade_time, buyer_is_market_maker
+                last_trade_id, trade_time, buyer_is_market_maker, market
             ) VALUES (
-                to_timestamp($1::FLOAT8), $2, $3, $4, $5, $6, $7, to_timestamp($8::FLOAT8), $9
-            )",
+                to_timestamp($1::FLOAT8), $2, $3, $4, $5, $6, $7, to_timestamp($8::FLOAT8), $9, $10
+            ) ON CONFLICT (market, symbol, aggregate_trade_id, trade_time) DO NOTHING",
             &[
                 &(agg_trade_event.E as f64 / 1000.0), // Event time in milliseconds
                 &agg_trade_event.s,                   // Symbol
@@ -25,6 +27,7 @@ pub async fn insert_agg_trade(
                 &(agg_trade_event.l as i64),          // Last trade ID
                 &(agg_trade_event.T as f64 / 1000.0), // Trade time in milliseconds
                 &agg_trade_event.m,                   // Buyer is market maker
+                &agg_trade_event.market.as_str(),     // Market
             ],
         )
         .await?;
@@ -45,7 +48,7 @@ pub async fn batch_insert_agg_trade(
     let base_query = String::from(
         "INSERT INTO binance.agg_trades (
         event_time, symbol, aggregate_trade_id, price, quantity, first_trade_id,
-        last_trade_id, trade_time, buyer_is_market_maker
+        last_trade_id, trade_time, buyer_is_market_maker, market
     ) VALUES ",
     );
 
@@ -53,11 +56,11 @@ pub async fn batch_insert_agg_trade(
     let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Sync + Send>> = Vec::new();
 
     for (i, agg_trade) in agg_trades.iter().enumerate() {
-        // Each record requires 9 parameters
-        let offset = i * 9;
+        // Each record requires 10 parameters
+        let offset = i * 10;
         placeholders.push(format!(
-        "(to_timestamp(${}::FLOAT8), ${}, ${}, ${}, ${}, ${}, ${}, to_timestamp(${}::FLOAT8), ${})",
-        offset + 1, offset + 2, offset + 3, offset + 4, offset + 5, offset + 6, offset + 7, offset + 8, offset + 9,
+        "(to_timestamp(${}::FLOAT8), ${}, ${}, ${}, ${}, ${}, ${}, to_timestamp(${}::FLOAT8), ${}, ${})",
+        offset + 1, offset + 2, offset + 3, offset + 4, offset + 5, offset + 6, offset + 7, offset + 8, offset + 9, offset + 10,
     ));
 
         // Add parameters
@@ -70,10 +73,15 @@ pub async fn batch_insert_agg_trade(
         params.push(Box::new(agg_trade.l as i64));
         params.push(Box::new(agg_trade.T as f64 / 1000.0));
         params.push(Box::new(agg_trade.m));
+        params.push(Box::new(agg_trade.market.as_str()));
     }
 
-    // Join all placeholders into final query
-    let query = format!("{}{}", base_query, placeholders.join(","));
+    // Join all placeholders into final query; backfilled trades may already be stored
+    let query = format!(
+        "{}{} ON CONFLICT (market, symbol, aggregate_trade_id, trade_time) DO NOTHING",
+        base_query,
+        placeholders.join(",")
+    );
     client
         .execute(
             &query,
@@ -86,3 +94,30 @@ pub async fn batch_insert_agg_trade(
 
     Ok(())
 }
+
+/// Last stored aggregate trade of every symbol of `market`:
+/// symbol -> (aggregate trade ID, trade time in Unix milliseconds).
+#[allow(dead_code)]
+pub async fn last_agg_trade_ids(
+    client: &Client,
+    market: BinanceMarket,
+) -> Result<HashMap<String, (u64, u64)>, Box<dyn std::error::Error>> {
+    let rows = client
+        .query(
+            "SELECT DISTINCT ON (symbol)
+                symbol, aggregate_trade_id, (extract(epoch FROM trade_time) * 1000)::BIGINT
+            FROM binance.agg_trades
+            WHERE market = $1
+            ORDER BY symbol, aggregate_trade_id DESC",
+            &[&market.as_str()],
+        )
+        .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| {
+            let last_trade = (row.get::<_, i64>(1) as u64, row.get::<_, i64>(2) as u64);
+            (row.get::<_, String>(0), last_trade)
+        })
+        .collect())
+}
@@ -0,0 +1,3 @@
+pub mod order_book;
+pub mod ticker;
+pub mod trade;
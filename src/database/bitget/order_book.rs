@@ -0,0 +1,95 @@
+use tokio_postgres::Client;
+
+use crate::cex::bitget::order_book::{BitgetChecksumEvent, BitgetDepthEvent};
+
+/// Writes the levels of each depth event as received: the full book for
+/// snapshots, the changed levels for updates.
+#[allow(dead_code)]
+pub async fn batch_insert_bitget_depth(
+    client: &Client,
+    depths: Vec<BitgetDepthEvent>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if depths.is_empty() {
+        return Ok(());
+    }
+
+    let base_query = String::from(
+        "INSERT INTO bitget.order_books (
+            time, symbol, inst_type, action, price_level, quantity, side
+        ) VALUES ",
+    );
+
+    let mut rows = Vec::new();
+    for event in &depths {
+        let sides = [("bid", &event.bids), ("ask", &event.asks)];
+        for (side, levels) in sides {
+            for (price, quantity) in levels {
+                rows.push((event, price, quantity.parse::<f32>()?, side));
+            }
+        }
+    }
+
+    for chunks in rows.chunks(100) {
+        let mut placeholders = Vec::new();
+        let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Sync + Send>> = Vec::new();
+
+        for (i, (event, price, quantity, side)) in chunks.iter().enumerate() {
+            // Each record requires 7 parameters
+            let offset = i * 7;
+            placeholders.push(format!(
+                "(to_timestamp(${}::FLOAT8), ${}, ${}, ${}, ${}, ${}, ${})",
+                offset + 1,
+                offset + 2,
+                offset + 3,
+                offset + 4,
+                offset + 5,
+                offset + 6,
+                offset + 7,
+            ));
+            params.push(Box::new(event.ts as f64 / 1000.0));
+            params.push(Box::new(event.symbol.clone()));
+            params.push(Box::new(event.inst_type.clone()));
+            params.push(Box::new(event.action.clone()));
+            params.push(Box::new((*price).clone()));
+            params.push(Box::new(*quantity));
+            params.push(Box::new(*side));
+        }
+
+        let query = format!("{}{}", base_query, placeholders.join(","));
+        client
+            .execute(
+                &query,
+                &params
+                    .iter()
+                    .map(|p| p.as_ref() as &(dyn tokio_postgres::types::ToSql + Sync))
+                    .collect::<Vec<_>>(),
+            )
+            .await?;
+    }
+    Ok(())
+}
+
+/// Records a checksum mismatch, a data quality metric of the Bitget books.
+#[allow(dead_code)]
+pub async fn insert_bitget_checksum_failure(
+    client: &Client,
+    checksum_event: BitgetChecksumEvent,
+) -> Result<(), Box<dyn std::error::Error>> {
+    client
+        .execute(
+            "INSERT INTO bitget.checksum_failures (
+                time, symbol, inst_type, channel, failures
+            ) VALUES (
+                to_timestamp($1::FLOAT8), $2, $3, $4, $5
+            )",
+            &[
+                &(checksum_event.ts as f64 / 1000.0), // Update time in seconds
+                &checksum_event.symbol,
+                &checksum_event.inst_type,
+                &checksum_event.channel,
+                &(checksum_event.failures as i64), // Mismatches since the collector started
+            ],
+        )
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use tokio_postgres::Client;
+
+use crate::cex::bitget::ticker::BitgetTickerEvent;
+
+// Futures only fields are stored as NULL for spot tickers
+fn parse_optional(value: &Option<String>) -> Result<Option<f32>, std::num::ParseFloatError> {
+    value.as_deref().map(str::parse::<f32>).transpose()
+}
+
+#[allow(dead_code)]
+pub async fn batch_insert_bitget_ticker(
+    client: &Client,
+    tickers: Vec<BitgetTickerEvent>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if tickers.is_empty() {
+        return Ok(());
+    }
+
+    let base_query = String::from(
+        "INSERT INTO bitget.tickers (
+            time, symbol, inst_type, last_price, bid_price, ask_price, bid_size, ask_size,
+            base_volume, quote_volume, mark_price, index_price, funding_rate,
+            next_funding_time, holding_amount
+        ) VALUES ",
+    );
+
+    let mut placeholders = Vec::new();
+    let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Sync + Send>> = Vec::new();
+
+    for (i, ticker) in tickers.iter().enumerate() {
+        // Each record requires 15 parameters
+        let offset = i * 15;
+        placeholders.push(format!(
+            "(to_timestamp(${}::FLOAT8), ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, to_timestamp(${}::FLOAT8), ${})",
+            offset + 1,
+            offset + 2,
+            offset + 3,
+            offset + 4,
+            offset + 5,
+            offset + 6,
+            offset + 7,
+            offset + 8,
+            offset + 9,
+            offset + 10,
+            offset + 11,
+            offset + 12,
+            offset + 13,
+            offset + 14,
+            offset + 15,
+        ));
+        params.push(Box::new(ticker.ts as f64 / 1000.0));
+        params.push(Box::new(ticker.symbol.clone()));
+        params.push(Box::new(ticker.inst_type.clone()));
+        params.push(Box::new(ticker.last_price.parse::<f32>()?));
+        params.push(Box::new(ticker.bid_price.parse::<f32>()?));
+        params.push(Box::new(ticker.ask_price.parse::<f32>()?));
+        params.push(Box::new(ticker.bid_size.parse::<f32>()?));
+        params.push(Box::new(ticker.ask_size.parse::<f32>()?));
+        params.push(Box::new(ticker.base_volume.parse::<f32>()?));
+        params.push(Box::new(ticker.quote_volume.parse::<f32>()?));
+        params.push(Box::new(parse_optional(&ticker.mark_price)?));
+        params.push(Box::new(parse_optional(&ticker.index_price)?));
+        params.push(Box::new(parse_optional(&ticker.funding_rate)?));
+        params.push(Box::new(
+            ticker.next_funding_time.map(|time| time as f64 / 1000.0),
+        ));
+        params.push(Box::new(parse_optional(&ticker.holding_amount)?));
+    }
+
+    let query = format!("{}{}", base_query, placeholders.join(","));
+    client
+        .execute(
+            &query,
+            &params
+                .iter()
+                .map(|p| p.as_ref() as &(dyn tokio_postgres::types::ToSql + Sync))
+                .collect::<Vec<_>>(),
+        )
+        .await?;
+
+    Ok(())
+}
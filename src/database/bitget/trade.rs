@@ -0,0 +1,62 @@
+use tokio_postgres::Client;
+
+use crate::cex::bitget::trade::BitgetTradeEvent;
+
+#[allow(dead_code)]
+pub async fn batch_insert_bitget_trade(
+    client: &Client,
+    trades: Vec<BitgetTradeEvent>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if trades.is_empty() {
+        return Ok(());
+    }
+
+    let base_query = String::from(
+        "INSERT INTO bitget.trades (
+            time, symbol, inst_type, trade_id, price, size, side
+        ) VALUES ",
+    );
+
+    let mut placeholders = Vec::new();
+    let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Sync + Send>> = Vec::new();
+
+    for (i, trade) in trades.iter().enumerate() {
+        // Each record requires 7 parameters
+        let offset = i * 7;
+        placeholders.push(format!(
+            "(to_timestamp(${}::FLOAT8), ${}, ${}, ${}, ${}, ${}, ${})",
+            offset + 1,
+            offset + 2,
+            offset + 3,
+            offset + 4,
+            offset + 5,
+            offset + 6,
+            offset + 7,
+        ));
+        params.push(Box::new(trade.ts as f64 / 1000.0));
+        params.push(Box::new(trade.symbol.clone()));
+        params.push(Box::new(trade.inst_type.clone()));
+        params.push(Box::new(trade.trade_id as i64));
+        params.push(Box::new(trade.price.parse::<f32>()?));
+        params.push(Box::new(trade.size.parse::<f32>()?));
+        params.push(Box::new(trade.side.clone()));
+    }
+
+    // Recent trades are replayed after every subscribe, so some are already stored
+    let query = format!(
+        "{}{} ON CONFLICT (inst_type, symbol, trade_id, time) DO NOTHING",
+        base_query,
+        placeholders.join(",")
+    );
+    client
+        .execute(
+            &query,
+            &params
+                .iter()
+                .map(|p| p.as_ref() as &(dyn tokio_postgres::types::ToSql + Sync))
+                .collect::<Vec<_>>(),
+        )
+        .await?;
+
+    Ok(())
+}
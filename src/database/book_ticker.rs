@@ -0,0 +1,63 @@
+use tokio_postgres::Client;
+
+use crate::cex::binance::book_ticker::BookTickerEvent;
+
+#[allow(dead_code)]
+pub async fn batch_insert_book_ticker(
+    client: &Client,
+    book_tickers: Vec<BookTickerEvent>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if book_tickers.is_empty() {
+        return Ok(());
+    }
+
+    let base_query = String::from(
+        "INSERT INTO binance.book_tickers (
+            event_time, symbol, update_id, best_bid_price, best_bid_quantity,
+            best_ask_price, best_ask_quantity, transaction_time, market
+        ) VALUES ",
+    );
+
+    let mut placeholders = Vec::new();
+    let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Sync + Send>> = Vec::new();
+
+    for (i, book_ticker) in book_tickers.iter().enumerate() {
+        // Each record requires 9 parameters
+        let offset = i * 9;
+        placeholders.push(format!(
+            "(to_timestamp(${}::FLOAT8), ${}, ${}, ${}, ${}, ${}, ${}, to_timestamp(${}::FLOAT8), ${})",
+            offset + 1,
+            offset + 2,
+            offset + 3,
+            offset + 4,
+            offset + 5,
+            offset + 6,
+            offset + 7,
+            offset + 8,
+            offset + 9,
+        ));
+
+        params.push(Box::new(book_ticker.E as f64 / 1000.0));
+        params.push(Box::new(book_ticker.s.clone()));
+        params.push(Box::new(book_ticker.u as i64));
+        params.push(Box::new(book_ticker.b.parse::<f32>()?));
+        params.push(Box::new(book_ticker.B.parse::<f32>()?));
+        params.push(Box::new(book_ticker.a.parse::<f32>()?));
+        params.push(Box::new(book_ticker.A.parse::<f32>()?));
+        params.push(Box::new(book_ticker.T as f64 / 1000.0));
+        params.push(Box::new(book_ticker.market.as_str()));
+    }
+
+    let query = format!("{}{}", base_query, placeholders.join(","));
+    client
+        .execute(
+            &query,
+            &params
+                .iter()
+                .map(|p| p.as_ref() as &(dyn tokio_postgres::types::ToSql + Sync))
+                .collect::<Vec<_>>(),
+        )
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,107 @@
+use std::collections::HashMap;
+
+use tokio_postgres::Client;
+
+use crate::cex::binance::kline::KlineEvent;
+
+/// Upserts klines keyed by (market, symbol, contract_type, interval, open_time).
+/// In-progress candles are overwritten until Binance marks them closed.
+#[allow(dead_code)]
+pub async fn batch_upsert_kline(
+    client: &Client,
+    klines: Vec<KlineEvent>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if klines.is_empty() {
+        return Ok(());
+    }
+
+    // A single statement can't update the same row twice, so keep the latest update per candle
+    let mut latest: HashMap<(&'static str, String, String, String, u64), KlineEvent> =
+        HashMap::new();
+    for kline in klines {
+        let key = (
+            kline.market.as_str(),
+            kline.s.clone(),
+            kline.contract_type.clone(),
+            kline.k.i.clone(),
+            kline.k.t,
+        );
+        match latest.get(&key) {
+            Some(existing) if existing.E > kline.E => {}
+            _ => {
+                latest.insert(key, kline);
+            }
+        }
+    }
+
+    let base_query = String::from(
+        "INSERT INTO binance.klines (
+            open_time, symbol, kline_interval, close_time, open, high, low, close, volume,
+            quote_volume, trade_count, taker_buy_volume, taker_buy_quote_volume, is_closed,
+            event_time, market, contract_type
+        ) VALUES ",
+    );
+
+    let mut placeholders = Vec::new();
+    let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Sync + Send>> = Vec::new();
+
+    for (i, kline) in latest.values().enumerate() {
+        // Each record requires 17 parameters
+        let offset = i * 17;
+        placeholders.push(format!(
+            "(to_timestamp(${}::FLOAT8), ${}, ${}, to_timestamp(${}::FLOAT8), ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, to_timestamp(${}::FLOAT8), ${}, ${})",
+            offset + 1, offset + 2, offset + 3, offset + 4, offset + 5, offset + 6, offset + 7, offset + 8,
+            offset + 9, offset + 10, offset + 11, offset + 12, offset + 13, offset + 14, offset + 15, offset + 16,
+            offset + 17,
+        ));
+
+        params.push(Box::new(kline.k.t as f64 / 1000.0));
+        params.push(Box::new(kline.s.clone()));
+        params.push(Box::new(kline.k.i.clone()));
+        params.push(Box::new(kline.k.T as f64 / 1000.0));
+        params.push(Box::new(kline.k.o.parse::<f32>()?));
+        params.push(Box::new(kline.k.h.parse::<f32>()?));
+        params.push(Box::new(kline.k.l.parse::<f32>()?));
+        params.push(Box::new(kline.k.c.parse::<f32>()?));
+        params.push(Box::new(kline.k.v.parse::<f32>()?));
+        params.push(Box::new(kline.k.q.parse::<f32>()?));
+        params.push(Box::new(kline.k.n as i64));
+        params.push(Box::new(kline.k.V.parse::<f32>()?));
+        params.push(Box::new(kline.k.Q.parse::<f32>()?));
+        params.push(Box::new(kline.k.x));
+        params.push(Box::new(kline.E as f64 / 1000.0));
+        params.push(Box::new(kline.market.as_str()));
+        params.push(Box::new(kline.contract_type.clone()));
+    }
+
+    // Closed candles are final and never overwritten by a late in-progress update
+    let query = format!(
+        "{}{} ON CONFLICT (market, symbol, contract_type, kline_interval, open_time) DO UPDATE SET
+            close_time = EXCLUDED.close_time,
+            open = EXCLUDED.open,
+            high = EXCLUDED.high,
+            low = EXCLUDED.low,
+            close = EXCLUDED.close,
+            volume = EXCLUDED.volume,
+            quote_volume = EXCLUDED.quote_volume,
+            trade_count = EXCLUDED.trade_count,
+            taker_buy_volume = EXCLUDED.taker_buy_volume,
+            taker_buy_quote_volume = EXCLUDED.taker_buy_quote_volume,
+            is_closed = EXCLUDED.is_closed,
+            event_time = EXCLUDED.event_time
+        WHERE NOT binance.klines.is_closed",
+        base_query,
+        placeholders.join(",")
+    );
+    client
+        .execute(
+            &query,
+            &params
+                .iter()
+                .map(|p| p.as_ref() as &(dyn tokio_postgres::types::ToSql + Sync))
+                .collect::<Vec<_>>(),
+        )
+        .await?;
+
+    Ok(())
+}
@@ -9,9 +9,9 @@ pub async fn insert_liquidation(
     client
         .execute(
             "INSERT INTO binance.liquidations (
-                event_time, symbol, side, order_type, time_in_force, quantity, price, avg_price, order_status, last_filled_quantity, total_filled_quantity, trade_time
+                event_time, symbol, side, order_type, time_in_force, quantity, price, avg_price, order_status, last_filled_quantity, total_filled_quantity, trade_time, market
             ) VALUES (
-                to_timestamp($1::FLOAT8), $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, to_timestamp($12::FLOAT8)
+                to_timestamp($1::FLOAT8), $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, to_timestamp($12::FLOAT8), $13
             )",
             &[
                 &(liquidation_event.E as f64 / 1000.0),                               // Event time in seconds
@@ -26,6 +26,7 @@ pub async fn insert_liquidation(
                 &liquidation_event.o.l.parse::<f32>()?,   // Last filled quantity
                 &liquidation_event.o.z.parse::<f32>()?,   // Total filled quantity
                 &(liquidation_event.o.T as f64 / 1000.0),                              // Trade time in seconds
+                &liquidation_event.market.as_str(),       // Market
             ],
         )
         .await?;
@@ -43,7 +44,7 @@ pub async fn batch_insert_liquidation(
 
     let base_query = String::from(
         "INSERT INTO binance.liquidations (
-            event_time, symbol, side, order_type, time_in_force, quantity, price, avg_price, order_status, last_filled_quantity, total_filled_quantity, trade_time
+            event_time, symbol, side, order_type, time_in_force, quantity, price, avg_price, order_status, last_filled_quantity, total_filled_quantity, trade_time, market
         ) VALUES ",
     );
 
@@ -62,6 +63,7 @@ pub async fn batch_insert_liquidation(
             liquid.o.l.parse::<f32>()?,
             liquid.o.z.parse::<f32>()?,
             liquid.o.T as f64 / 1000.0,
+            liquid.market.as_str(),
         ));
     }
 
@@ -83,11 +85,12 @@ pub async fn batch_insert_liquidation(
             last_filled_quantity,
             total_filled_quantity,
             trade_time,
+            market,
         ) in chunks
         {
             placeholders.push(format!(
-                "(to_timestamp(${}::FLOAT8), ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, to_timestamp(${}::FLOAT8))",
-                param_index, param_index + 1, param_index + 2, param_index + 3, param_index + 4, param_index + 5, param_index + 6, param_index + 7, param_index + 8, param_index + 9, param_index + 10, param_index + 11
+                "(to_timestamp(${}::FLOAT8), ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, ${}, to_timestamp(${}::FLOAT8), ${})",
+                param_index, param_index + 1, param_index + 2, param_index + 3, param_index + 4, param_index + 5, param_index + 6, param_index + 7, param_index + 8, param_index + 9, param_index + 10, param_index + 11, param_index + 12
             ));
             params.push(Box::new(event_time));
             params.push(Box::new(symbol));
@@ -101,7 +104,8 @@ pub async fn batch_insert_liquidation(
             params.push(Box::new(last_filled_quantity));
             params.push(Box::new(total_filled_quantity));
             params.push(Box::new(trade_time));
-            param_index += 11;
+            params.push(Box::new(market));
+            param_index += 13;
         }
 
         let query = format!("{}{}", base_query, placeholders.join(","));
@@ -0,0 +1,98 @@
+use tokio_postgres::Client;
+
+use crate::cex::binance::mark_price::MarkPriceEvent;
+
+// Delivery contracts have no funding and send an empty rate, stored as NULL
+fn parse_funding_rate(rate: &str) -> Result<Option<f32>, std::num::ParseFloatError> {
+    if rate.is_empty() {
+        return Ok(None);
+    }
+    rate.parse::<f32>().map(Some)
+}
+
+#[allow(dead_code)]
+pub async fn insert_mark_price(
+    client: &Client,
+    mark_price_event: MarkPriceEvent,
+) -> Result<(), Box<dyn std::error::Error>> {
+    client
+        .execute(
+            "INSERT INTO binance.mark_prices (
+                event_time, symbol, mark_price, index_price, estimated_settle_price,
+                funding_rate, next_funding_time, market
+            ) VALUES (
+                to_timestamp($1::FLOAT8), $2, $3, $4, $5, $6, to_timestamp($7::FLOAT8), $8
+            )",
+            &[
+                &(mark_price_event.E as f64 / 1000.0), // Event time in milliseconds
+                &mark_price_event.s,                   // Symbol
+                &mark_price_event.p.parse::<f32>()?,   // Mark price
+                &mark_price_event.i.parse::<f32>()?,   // Index price
+                &mark_price_event.P.parse::<f32>()?,   // Estimated settle price
+                &parse_funding_rate(&mark_price_event.r)?, // Funding rate
+                &(mark_price_event.T as f64 / 1000.0), // Next funding time in milliseconds
+                &mark_price_event.market.as_str(),     // Market
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+#[allow(dead_code)]
+pub async fn batch_insert_mark_price(
+    client: &Client,
+    mark_prices: Vec<MarkPriceEvent>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if mark_prices.is_empty() {
+        return Ok(());
+    }
+
+    let base_query = String::from(
+        "INSERT INTO binance.mark_prices (
+            event_time, symbol, mark_price, index_price, estimated_settle_price,
+            funding_rate, next_funding_time, market
+        ) VALUES ",
+    );
+
+    let mut placeholders = Vec::new();
+    let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Sync + Send>> = Vec::new();
+
+    for (i, mark_price) in mark_prices.iter().enumerate() {
+        // Each record requires 8 parameters
+        let offset = i * 8;
+        placeholders.push(format!(
+            "(to_timestamp(${}::FLOAT8), ${}, ${}, ${}, ${}, ${}, to_timestamp(${}::FLOAT8), ${})",
+            offset + 1,
+            offset + 2,
+            offset + 3,
+            offset + 4,
+            offset + 5,
+            offset + 6,
+            offset + 7,
+            offset + 8,
+        ));
+
+        params.push(Box::new(mark_price.E as f64 / 1000.0));
+        params.push(Box::new(mark_price.s.clone()));
+        params.push(Box::new(mark_price.p.parse::<f32>()?));
+        params.push(Box::new(mark_price.i.parse::<f32>()?));
+        params.push(Box::new(mark_price.P.parse::<f32>()?));
+        params.push(Box::new(parse_funding_rate(&mark_price.r)?));
+        params.push(Box::new(mark_price.T as f64 / 1000.0));
+        params.push(Box::new(mark_price.market.as_str()));
+    }
+
+    let query = format!("{}{}", base_query, placeholders.join(","));
+    client
+        .execute(
+            &query,
+            &params
+                .iter()
+                .map(|p| p.as_ref() as &(dyn tokio_postgres::types::ToSql + Sync))
+                .collect::<Vec<_>>(),
+        )
+        .await?;
+
+    Ok(())
+}
@@ -1,6 +1,13 @@
 pub mod agg_trade;
+pub mod bitget;
+pub mod book_ticker;
+pub mod kline;
 pub mod liquidation;
+pub mod mark_price;
+pub mod open_interest;
 pub mod order_book;
+pub mod partial_depth;
 pub mod postgres;
+pub mod sentiment;
 
 pub mod features;
@@ -0,0 +1,62 @@
+use tokio_postgres::Client;
+
+use crate::cex::binance::open_interest::OpenInterestEvent;
+
+/// Points already stored (e.g. overlapping backfills) are skipped.
+#[allow(dead_code)]
+pub async fn batch_insert_open_interest(
+    client: &Client,
+    open_interests: Vec<OpenInterestEvent>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if open_interests.is_empty() {
+        return Ok(());
+    }
+
+    let base_query = String::from(
+        "INSERT INTO binance.open_interest (
+            time, symbol, open_interest, open_interest_value, market
+        ) VALUES ",
+    );
+
+    let mut placeholders = Vec::new();
+    let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Sync + Send>> = Vec::new();
+
+    for (i, open_interest) in open_interests.iter().enumerate() {
+        // Each record requires 5 parameters
+        let offset = i * 5;
+        placeholders.push(format!(
+            "(to_timestamp(${}::FLOAT8), ${}, ${}, ${}, ${})",
+            offset + 1,
+            offset + 2,
+            offset + 3,
+            offset + 4,
+            offset + 5,
+        ));
+
+        params.push(Box::new(open_interest.time as f64 / 1000.0));
+        params.push(Box::new(open_interest.symbol.clone()));
+        params.push(Box::new(open_interest.open_interest.parse::<f32>()?));
+        params.push(Box::new(match &open_interest.open_interest_value {
+            Some(value) => Some(value.parse::<f32>()?),
+            None => None,
+        }));
+        params.push(Box::new(open_interest.market.as_str()));
+    }
+
+    let query = format!(
+        "{}{} ON CONFLICT (market, symbol, time) DO NOTHING",
+        base_query,
+        placeholders.join(",")
+    );
+    client
+        .execute(
+            &query,
+            &params
+                .iter()
+                .map(|p| p.as_ref() as &(dyn tokio_postgres::types::ToSql + Sync))
+                .collect::<Vec<_>>(),
+        )
+        .await?;
+
+    Ok(())
+}
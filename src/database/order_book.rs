@@ -1,29 +1,32 @@
-use std::collections::HashMap;
 use std::time::SystemTime;
 use tokio_postgres::Client;
 
+use crate::cex::{binance::market::BinanceMarket, combined_order_book::Level};
+
 #[allow(dead_code)]
 pub async fn insert_order_book(
     client: &Client,
     time: chrono::DateTime<chrono::Utc>,
-    bids: HashMap<String, String>,
-    asks: HashMap<String, String>,
+    symbol: &str,
+    market: BinanceMarket,
+    bids: Vec<Level>,
+    asks: Vec<Level>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let time_sys: SystemTime = time.into();
-    for (price, quantity) in bids.iter() {
+    for level in bids.iter() {
         client
             .execute(
-                "INSERT INTO binance.order_books (time, price_level, quantity, side) VALUES ($1, $2, $3, 'bid')",
-                &[&time_sys, &price, &quantity.parse::<f32>().unwrap()],
+                "INSERT INTO binance.order_books (time, symbol, market, price_level, quantity, side) VALUES ($1, $2, $3, $4, $5, 'bid')",
+                &[&time_sys, &symbol, &market.as_str(), &level.price.to_string(), &(level.quantity as f32)],
             )
             .await?;
     }
 
-    for (price, quantity) in asks.iter() {
+    for level in asks.iter() {
         client
             .execute(
-                "INSERT INTO binance.order_books (time, price_level, quantity, side) VALUES ($1, $2, $3, 'ask')",
-                &[&time_sys, &price, &quantity.parse::<f32>().unwrap()],
+                "INSERT INTO binance.order_books (time, symbol, market, price_level, quantity, side) VALUES ($1, $2, $3, $4, $5, 'ask')",
+                &[&time_sys, &symbol, &market.as_str(), &level.price.to_string(), &(level.quantity as f32)],
             )
             .await?;
     }
@@ -34,26 +37,41 @@ pub async fn insert_order_book(
 #[allow(dead_code)]
 pub async fn batch_insert_order_book(
     client: &Client,
-    bids: Vec<(HashMap<String, String>, f64)>,
-    asks: Vec<(HashMap<String, String>, f64)>,
+    bids: Vec<(String, BinanceMarket, Vec<Level>, f64)>,
+    asks: Vec<(String, BinanceMarket, Vec<Level>, f64)>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     if bids.is_empty() && asks.is_empty() {
         return Ok(());
     }
 
-    let base_query =
-        String::from("INSERT INTO binance.order_books (time, price_level, quantity, side) VALUES ");
+    let base_query = String::from(
+        "INSERT INTO binance.order_books (time, symbol, market, price_level, quantity, side) VALUES ",
+    );
 
     let mut combined_data = Vec::new();
-    for (bid_map, time) in bids {
-        for (price, quantity) in bid_map {
-            combined_data.push((time, price, quantity.parse::<f32>()?, "bid"));
+    for (symbol, market, bid_levels, time) in bids {
+        for level in bid_levels {
+            combined_data.push((
+                time,
+                symbol.clone(),
+                market.as_str(),
+                level.price.to_string(),
+                level.quantity as f32,
+                "bid",
+            ));
         }
     }
 
-    for (ask_map, time) in asks {
-        for (price, quantity) in ask_map {
-            combined_data.push((time, price, quantity.parse::<f32>()?, "ask"));
+    for (symbol, market, ask_levels, time) in asks {
+        for level in ask_levels {
+            combined_data.push((
+                time,
+                symbol.clone(),
+                market.as_str(),
+                level.price.to_string(),
+                level.quantity as f32,
+                "ask",
+            ));
         }
     }
 
@@ -62,19 +80,23 @@ pub async fn batch_insert_order_book(
         let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Sync + Send>> = Vec::new();
         let mut param_index = 1;
 
-        for (time, price, quantity, side) in chunks {
+        for (time, symbol, market, price, quantity, side) in chunks {
             placeholders.push(format!(
-                "(to_timestamp(${}::FLOAT8), ${}, ${}, ${})",
+                "(to_timestamp(${}::FLOAT8), ${}, ${}, ${}, ${}, ${})",
                 param_index,
                 param_index + 1,
                 param_index + 2,
-                param_index + 3
+                param_index + 3,
+                param_index + 4,
+                param_index + 5
             ));
             params.push(Box::new(time / 1000.0));
+            params.push(Box::new(symbol));
+            params.push(Box::new(market));
             params.push(Box::new(price));
             params.push(Box::new(quantity));
             params.push(Box::new(side));
-            param_index += 4;
+            param_index += 6;
         }
 
         let query = format!("{}{}", base_query, placeholders.join(","));
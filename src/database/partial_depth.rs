@@ -0,0 +1,70 @@
+use tokio_postgres::Client;
+
+use crate::cex::binance::partial_depth::PartialDepthEvent;
+
+#[allow(dead_code)]
+pub async fn batch_insert_partial_depth(
+    client: &Client,
+    partial_depths: Vec<PartialDepthEvent>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if partial_depths.is_empty() {
+        return Ok(());
+    }
+
+    let base_query = String::from(
+        "INSERT INTO binance.partial_depths (
+            time, symbol, market, levels, last_update_id, price_level, quantity, side
+        ) VALUES ",
+    );
+
+    let mut rows = Vec::new();
+    for event in &partial_depths {
+        let sides = [("bid", &event.bids), ("ask", &event.asks)];
+        for (side, levels) in sides {
+            for (price, quantity) in levels {
+                rows.push((event, price, quantity.parse::<f32>()?, side));
+            }
+        }
+    }
+
+    for chunks in rows.chunks(100) {
+        let mut placeholders = Vec::new();
+        let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Sync + Send>> = Vec::new();
+
+        for (i, (event, price, quantity, side)) in chunks.iter().enumerate() {
+            // Each record requires 8 parameters
+            let offset = i * 8;
+            placeholders.push(format!(
+                "(to_timestamp(${}::FLOAT8), ${}, ${}, ${}, ${}, ${}, ${}, ${})",
+                offset + 1,
+                offset + 2,
+                offset + 3,
+                offset + 4,
+                offset + 5,
+                offset + 6,
+                offset + 7,
+                offset + 8,
+            ));
+            params.push(Box::new(event.event_time as f64 / 1000.0));
+            params.push(Box::new(event.symbol.clone()));
+            params.push(Box::new(event.market.as_str()));
+            params.push(Box::new(event.levels as i16));
+            params.push(Box::new(event.last_update_id as i64));
+            params.push(Box::new((*price).clone()));
+            params.push(Box::new(*quantity));
+            params.push(Box::new(*side));
+        }
+
+        let query = format!("{}{}", base_query, placeholders.join(","));
+        client
+            .execute(
+                &query,
+                &params
+                    .iter()
+                    .map(|p| p.as_ref() as &(dyn tokio_postgres::types::ToSql + Sync))
+                    .collect::<Vec<_>>(),
+            )
+            .await?;
+    }
+    Ok(())
+}
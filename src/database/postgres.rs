@@ -6,17 +6,93 @@ use crate::database::{
     order_book::batch_insert_order_book,
 };
 use crate::{
-    cex::binance::websocket::BinanceData,
+    cex::binance::{
+        contract_info::ContractInfoEvent,
+        user_data::UserDataEvent,
+        websocket::{BinanceData, ReconnectEvent},
+    },
+    cex::bitget::{
+        order_book::BitgetDepthEvent, ticker::BitgetTickerEvent, trade::BitgetTradeEvent,
+        websocket::BitgetData,
+    },
+    cex::combined_order_book::{CombinedOrderBook, Side, VenueBook},
     database::{
         agg_trade::{batch_insert_agg_trade, insert_agg_trade},
+        bitget::{
+            order_book::{batch_insert_bitget_depth, insert_bitget_checksum_failure},
+            ticker::batch_insert_bitget_ticker,
+            trade::batch_insert_bitget_trade,
+        },
+        book_ticker::batch_insert_book_ticker,
+        kline::batch_upsert_kline,
         liquidation::{batch_insert_liquidation, insert_liquidation},
+        mark_price::{batch_insert_mark_price, insert_mark_price},
+        open_interest::batch_insert_open_interest,
         order_book::insert_order_book,
+        partial_depth::batch_insert_partial_depth,
+        sentiment::{batch_insert_long_short_ratio, batch_insert_taker_volume},
     },
 };
-use log::{error, info};
+use log::{error, info, warn};
+use std::collections::HashMap;
 use tokio::sync::mpsc;
 use tokio_postgres::{Client, NoTls};
 
+fn log_reconnect(event: &ReconnectEvent) {
+    warn!(
+        "{} streams {} reconnected after attempt {}: data between {} and {} may be missing",
+        event.symbol,
+        event.streams.join(", "),
+        event.attempt,
+        event.disconnected_at,
+        event.reconnected_at
+    );
+}
+
+// Contract lifecycle changes are acted on by the streams, only traced here
+fn log_contract_info(event: &ContractInfoEvent) {
+    if event.bks.is_empty() {
+        info!(
+            "{} {} contract {} is {}",
+            event.market, event.ct, event.s, event.cs
+        );
+    } else {
+        info!(
+            "{} {} contract {} is {}, {} leverage brackets updated",
+            event.market,
+            event.ct,
+            event.s,
+            event.cs,
+            event.bks.len()
+        );
+    }
+}
+
+// Account data is not persisted, only traced
+fn log_user_data(event: &UserDataEvent) {
+    match event {
+        UserDataEvent::OrderTradeUpdate(event) => info!(
+            "Order {} {} {} {}: {} filled {} at {}",
+            event.o.i, event.o.s, event.o.S, event.o.X, event.o.x, event.o.l, event.o.L
+        ),
+        UserDataEvent::AccountUpdate(event) => info!(
+            "Account update ({}): {} balances, {} positions",
+            event.a.m,
+            event.a.B.len(),
+            event.a.P.len()
+        ),
+        UserDataEvent::MarginCall(event) => warn!(
+            "Margin call on {}",
+            event
+                .p
+                .iter()
+                .map(|position| position.s.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
 #[allow(dead_code)]
 pub async fn connect_to_timescaledb() -> Result<Client, Box<dyn std::error::Error>> {
     let connection_str =
@@ -42,17 +118,26 @@ pub async fn timescale_writer(
         match event {
             BinanceData::OrderBook(order_book_update) => {
                 let time = chrono::Utc::now();
+                let market = order_book_update.venue.binance_market().unwrap_or_default();
                 if let Err(e) = insert_order_book(
                     &client,
                     time,
-                    order_book_update.bids,
-                    order_book_update.asks,
+                    &order_book_update.symbol,
+                    market,
+                    order_book_update.book.levels(Side::Bid).collect(),
+                    order_book_update.book.levels(Side::Ask).collect(),
                 )
                 .await
                 {
                     error!("Failed to insert order book update: {}", e);
                 }
             }
+            BinanceData::PartialDepth(partial_depth_event) => {
+                if let Err(e) = batch_insert_partial_depth(&client, vec![partial_depth_event]).await
+                {
+                    error!("Failed to insert partial depth event: {}", e);
+                }
+            }
             BinanceData::Liquidation(liquidation_event) => {
                 if let Err(e) = insert_liquidation(&client, liquidation_event).await {
                     error!("Failed to insert liquidation event: {}", e);
@@ -63,47 +148,146 @@ pub async fn timescale_writer(
                     error!("Failed to insert agg trade event: {}", e);
                 }
             }
+            BinanceData::MarkPrice(mark_price_event) => {
+                if let Err(e) = insert_mark_price(&client, mark_price_event).await {
+                    error!("Failed to insert mark price event: {}", e);
+                }
+            }
+            BinanceData::Kline(kline_event) => {
+                if let Err(e) = batch_upsert_kline(&client, vec![kline_event]).await {
+                    error!("Failed to upsert kline event: {}", e);
+                }
+            }
+            BinanceData::BookTicker(book_ticker_event) => {
+                if let Err(e) = batch_insert_book_ticker(&client, vec![book_ticker_event]).await {
+                    error!("Failed to insert book ticker event: {}", e);
+                }
+            }
+            BinanceData::OpenInterest(open_interest_event) => {
+                if let Err(e) = batch_insert_open_interest(&client, vec![open_interest_event]).await
+                {
+                    error!("Failed to insert open interest: {}", e);
+                }
+            }
+            BinanceData::LongShortRatio(ratio_event) => {
+                if let Err(e) = batch_insert_long_short_ratio(&client, vec![ratio_event]).await {
+                    error!("Failed to insert long/short ratio: {}", e);
+                }
+            }
+            BinanceData::TakerVolume(volume_event) => {
+                if let Err(e) = batch_insert_taker_volume(&client, vec![volume_event]).await {
+                    error!("Failed to insert taker volume: {}", e);
+                }
+            }
+            BinanceData::ContractInfo(contract_event) => log_contract_info(&contract_event),
+            BinanceData::UserData(user_data_event) => log_user_data(&user_data_event),
+            BinanceData::Reconnect(reconnect_event) => log_reconnect(&reconnect_event),
         }
     }
 
     Ok(())
 }
 
+async fn write_order_book_features(
+    client: &Client,
+    time: f64,
+    bids: HashMap<String, String>,
+    asks: HashMap<String, String>,
+    current_price: String,
+) {
+    let feature_one_05 =
+        calculate_feature_one(bids.clone(), asks.clone(), current_price.clone(), 0.05);
+    let feature_one_10 = calculate_feature_one(bids, asks, current_price, 0.1);
+
+    if let Err(e) = insert_feature_one(client, time, feature_one_05, 0.05).await {
+        error!("Failed to insert order book imbalance: {}", e);
+    }
+
+    if let Err(e) = insert_feature_one(client, time, feature_one_10, 0.1).await {
+        error!("Failed to insert order book imbalance: {}", e);
+    }
+}
+
+/// Replaces the venue's book in the combined book of its symbol.
+fn merge_order_book(
+    books: &mut HashMap<String, CombinedOrderBook>,
+    venue_book: VenueBook,
+) -> &CombinedOrderBook {
+    let combined = books
+        .entry(venue_book.symbol.clone())
+        .or_insert_with(|| CombinedOrderBook::new(&venue_book.symbol));
+    combined.insert(venue_book);
+    combined
+}
+
+/// Writes strategy features. Order book features are computed on every Binance
+/// book update from the books of Binance and, when streamed, Bitget combined.
 #[allow(dead_code)]
 pub async fn feature_writer(
     mut rx: mpsc::Receiver<BinanceData>,
+    mut bitget_rx: mpsc::Receiver<BitgetData>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     info!("Starting feature writer");
     let client = connect_to_timescaledb().await?;
-    let mut current_price = String::from("0.0");
-
-    while let Some(event) = rx.recv().await {
-        match event {
-            BinanceData::OrderBook(order_book_update) => {
-                let time = order_book_update.time as f64;
-                let feature_one_05 = calculate_feature_one(
-                    order_book_update.bids.clone(),
-                    order_book_update.asks.clone(),
-                    current_price.clone(),
-                    0.05,
-                );
-                let feature_one_10 = calculate_feature_one(
-                    order_book_update.bids.clone(),
-                    order_book_update.asks.clone(),
-                    current_price.clone(),
-                    0.1,
-                );
+    let mut current_prices: HashMap<String, String> = HashMap::new();
+    let mut last_trade_times: HashMap<String, u64> = HashMap::new();
+    let mut books: HashMap<String, CombinedOrderBook> = HashMap::new();
 
-                if let Err(e) = insert_feature_one(&client, time, feature_one_05, 0.05).await {
-                    error!("Failed to insert order book imbalance: {}", e);
+    loop {
+        let event = tokio::select! {
+            event = rx.recv() => match event {
+                Some(event) => event,
+                None => break,
+            },
+            // Disabled once no Bitget stream is left
+            Some(event) = bitget_rx.recv() => {
+                if let BitgetData::OrderBook(venue_book) = event {
+                    merge_order_book(&mut books, venue_book);
                 }
+                continue;
+            }
+        };
 
-                if let Err(e) = insert_feature_one(&client, time, feature_one_10, 0.1).await {
-                    error!("Failed to insert order book imbalance: {}", e);
-                }
+        match event {
+            BinanceData::OrderBook(order_book_update) => {
+                let current_price = current_prices
+                    .get(&order_book_update.symbol)
+                    .cloned()
+                    .unwrap_or_else(|| String::from("0.0"));
+                let time = order_book_update.book.time;
+                let consolidated = merge_order_book(&mut books, order_book_update).consolidated();
+                write_order_book_features(
+                    &client,
+                    time as f64,
+                    consolidated.book.to_string_map(Side::Bid),
+                    consolidated.book.to_string_map(Side::Ask),
+                    current_price,
+                )
+                .await;
+            }
+            BinanceData::PartialDepth(partial_depth_event) => {
+                let current_price = current_prices
+                    .get(&partial_depth_event.symbol)
+                    .cloned()
+                    .unwrap_or_else(|| String::from("0.0"));
+                write_order_book_features(
+                    &client,
+                    partial_depth_event.event_time as f64,
+                    partial_depth_event.bids.into_iter().collect(),
+                    partial_depth_event.asks.into_iter().collect(),
+                    current_price,
+                )
+                .await;
             }
             BinanceData::AggTrade(agg_trade_event) => {
-                current_price = agg_trade_event.p.clone();
+                // Backfilled trades arrive after newer live ones
+                let last_time = last_trade_times
+                    .entry(agg_trade_event.s.clone())
+                    .or_default();
+                if agg_trade_event.T >= *last_time {
+                    *last_time = agg_trade_event.T;
+                    current_prices.insert(agg_trade_event.s, agg_trade_event.p);
+                }
             }
             _ => {}
         }
@@ -112,18 +296,97 @@ pub async fn feature_writer(
     Ok(())
 }
 
+/// Pending Bitget rows of the batch writer.
+#[derive(Default)]
+struct BitgetBatches {
+    depths: Vec<BitgetDepthEvent>,
+    trades: Vec<BitgetTradeEvent>,
+    tickers: Vec<BitgetTickerEvent>,
+}
+
+impl BitgetBatches {
+    async fn push(&mut self, client: &Client, event: BitgetData, batch_size: usize) {
+        match event {
+            BitgetData::Depth(depth_event) => {
+                self.depths.push(depth_event);
+                if self.depths.len() >= batch_size {
+                    if let Err(e) =
+                        batch_insert_bitget_depth(client, std::mem::take(&mut self.depths)).await
+                    {
+                        error!("Failed to insert Bitget depth events: {}", e);
+                    }
+                }
+            }
+            BitgetData::Trade(trade_event) => {
+                self.trades.push(trade_event);
+                if self.trades.len() >= batch_size {
+                    if let Err(e) =
+                        batch_insert_bitget_trade(client, std::mem::take(&mut self.trades)).await
+                    {
+                        error!("Failed to insert Bitget trade events: {}", e);
+                    }
+                }
+            }
+            BitgetData::Ticker(ticker_event) => {
+                self.tickers.push(ticker_event);
+                if self.tickers.len() >= batch_size {
+                    if let Err(e) =
+                        batch_insert_bitget_ticker(client, std::mem::take(&mut self.tickers)).await
+                    {
+                        error!("Failed to insert Bitget ticker events: {}", e);
+                    }
+                }
+            }
+            // Only sent to the feature writer, see `with_book_snapshots`
+            BitgetData::OrderBook(_) => {}
+            // Rare, written right away
+            BitgetData::ChecksumFailure(checksum_event) => {
+                if let Err(e) = insert_bitget_checksum_failure(client, checksum_event).await {
+                    error!("Failed to insert Bitget checksum failure: {}", e);
+                }
+            }
+        }
+    }
+}
+
 #[allow(dead_code)]
 pub async fn timescale_batch_writer(
     mut rx: mpsc::Receiver<BinanceData>,
+    mut bitget_rx: mpsc::Receiver<BitgetData>,
 ) -> Result<(), Box<dyn std::error::Error>> {
     info!("Starting raw data writer");
     let mut order_book_bids = Vec::new();
     let mut order_book_asks = Vec::new();
+    let mut partial_depths = Vec::new();
     let mut liquidations = Vec::new();
     let mut agg_trades = Vec::new();
+    let mut mark_prices = Vec::new();
+    let mut klines = Vec::new();
+    let mut book_tickers = Vec::new();
+    let mut open_interests = Vec::new();
+    let mut long_short_ratios = Vec::new();
+    let mut taker_volumes = Vec::new();
+    let mut bitget = BitgetBatches::default();
 
     let client = connect_to_timescaledb().await?;
-    while let Some(event) = rx.recv().await {
+    loop {
+        let event = tokio::select! {
+            event = rx.recv() => match event {
+                Some(event) => event,
+                None => break,
+            },
+            // Disabled once no Bitget stream is left
+            Some(event) = bitget_rx.recv() => {
+                let batch_size = if bitget_rx.max_capacity() - bitget_rx.capacity() > 1000 {
+                    500
+                } else {
+                    100
+                };
+                bitget.push(&client, event, batch_size).await;
+                continue;
+            }
+        };
+
         // Dynamic batch size adjustment based on the buffer usage
         let batch_size = if 9999 - rx.capacity() > 1000 {
             500
@@ -133,9 +396,20 @@ pub async fn timescale_batch_writer(
 
         match event {
             BinanceData::OrderBook(order_book_update) => {
-                let time = order_book_update.time as f64;
-                order_book_bids.push((order_book_update.bids.clone(), time));
-                order_book_asks.push((order_book_update.asks.clone(), time));
+                let time = order_book_update.book.time as f64;
+                let market = order_book_update.venue.binance_market().unwrap_or_default();
+                order_book_bids.push((
+                    order_book_update.symbol.clone(),
+                    market,
+                    order_book_update.book.levels(Side::Bid).collect(),
+                    time,
+                ));
+                order_book_asks.push((
+                    order_book_update.symbol.clone(),
+                    market,
+                    order_book_update.book.levels(Side::Ask).collect(),
+                    time,
+                ));
 
                 if order_book_bids.len() >= batch_size || order_book_asks.len() >= batch_size {
                     if let Err(e) = batch_insert_order_book(
@@ -149,6 +423,17 @@ pub async fn timescale_batch_writer(
                     }
                 }
             }
+            BinanceData::PartialDepth(partial_depth_event) => {
+                partial_depths.push(partial_depth_event);
+                if partial_depths.len() >= batch_size {
+                    if let Err(e) =
+                        batch_insert_partial_depth(&client, std::mem::take(&mut partial_depths))
+                            .await
+                    {
+                        error!("Failed to insert partial depth events: {}", e);
+                    }
+                }
+            }
             BinanceData::Liquidation(liquidation_event) => {
                 liquidations.push(liquidation_event);
                 // Liquidations are less frequent, so we can batch them less frequently
@@ -170,6 +455,75 @@ pub async fn timescale_batch_writer(
                     }
                 }
             }
+            BinanceData::MarkPrice(mark_price_event) => {
+                mark_prices.push(mark_price_event);
+                // One update per symbol per second
+                if mark_prices.len() >= batch_size / 10 {
+                    if let Err(e) =
+                        batch_insert_mark_price(&client, std::mem::take(&mut mark_prices)).await
+                    {
+                        error!("Failed to insert mark price events: {}", e);
+                    }
+                }
+            }
+            BinanceData::Kline(kline_event) => {
+                // Flush right away when a candle closes so finalized bars land promptly
+                let is_closed = kline_event.k.x;
+                klines.push(kline_event);
+                if klines.len() >= batch_size || is_closed {
+                    if let Err(e) = batch_upsert_kline(&client, std::mem::take(&mut klines)).await {
+                        error!("Failed to upsert kline events: {}", e);
+                    }
+                }
+            }
+            BinanceData::BookTicker(book_ticker_event) => {
+                book_tickers.push(book_ticker_event);
+                if book_tickers.len() >= batch_size {
+                    if let Err(e) =
+                        batch_insert_book_ticker(&client, std::mem::take(&mut book_tickers)).await
+                    {
+                        error!("Failed to insert book ticker events: {}", e);
+                    }
+                }
+            }
+            BinanceData::OpenInterest(open_interest_event) => {
+                open_interests.push(open_interest_event);
+                // Polled, so far less frequent than streamed data
+                if open_interests.len() >= batch_size / 10 {
+                    if let Err(e) =
+                        batch_insert_open_interest(&client, std::mem::take(&mut open_interests))
+                            .await
+                    {
+                        error!("Failed to insert open interest: {}", e);
+                    }
+                }
+            }
+            BinanceData::LongShortRatio(ratio_event) => {
+                long_short_ratios.push(ratio_event);
+                if long_short_ratios.len() >= batch_size / 10 {
+                    if let Err(e) = batch_insert_long_short_ratio(
+                        &client,
+                        std::mem::take(&mut long_short_ratios),
+                    )
+                    .await
+                    {
+                        error!("Failed to insert long/short ratios: {}", e);
+                    }
+                }
+            }
+            BinanceData::TakerVolume(volume_event) => {
+                taker_volumes.push(volume_event);
+                if taker_volumes.len() >= batch_size / 10 {
+                    if let Err(e) =
+                        batch_insert_taker_volume(&client, std::mem::take(&mut taker_volumes)).await
+                    {
+                        error!("Failed to insert taker volumes: {}", e);
+                    }
+                }
+            }
+            BinanceData::ContractInfo(contract_event) => log_contract_info(&contract_event),
+            BinanceData::UserData(user_data_event) => log_user_data(&user_data_event),
+            BinanceData::Reconnect(reconnect_event) => log_reconnect(&reconnect_event),
         }
     }
 
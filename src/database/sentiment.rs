@@ -0,0 +1,125 @@
+use tokio_postgres::Client;
+
+use crate::cex::binance::sentiment::{LongShortRatioEvent, TakerVolumeEvent};
+
+/// Points already stored are skipped.
+#[allow(dead_code)]
+pub async fn batch_insert_long_short_ratio(
+    client: &Client,
+    ratios: Vec<LongShortRatioEvent>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if ratios.is_empty() {
+        return Ok(());
+    }
+
+    let base_query = String::from(
+        "INSERT INTO binance.long_short_ratios (
+            time, symbol, ratio_type, period, long_short_ratio, long_share, short_share, market
+        ) VALUES ",
+    );
+
+    let mut placeholders = Vec::new();
+    let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Sync + Send>> = Vec::new();
+
+    for (i, ratio) in ratios.iter().enumerate() {
+        // Each record requires 8 parameters
+        let offset = i * 8;
+        placeholders.push(format!(
+            "(to_timestamp(${}::FLOAT8), ${}, ${}, ${}, ${}, ${}, ${}, ${})",
+            offset + 1,
+            offset + 2,
+            offset + 3,
+            offset + 4,
+            offset + 5,
+            offset + 6,
+            offset + 7,
+            offset + 8,
+        ));
+
+        params.push(Box::new(ratio.time as f64 / 1000.0));
+        params.push(Box::new(ratio.symbol.clone()));
+        params.push(Box::new(ratio.kind.as_str()));
+        params.push(Box::new(ratio.period.clone()));
+        params.push(Box::new(ratio.long_short_ratio.parse::<f32>()?));
+        params.push(Box::new(ratio.long_share.parse::<f32>()?));
+        params.push(Box::new(ratio.short_share.parse::<f32>()?));
+        params.push(Box::new(ratio.market.as_str()));
+    }
+
+    let query = format!(
+        "{}{} ON CONFLICT (market, symbol, ratio_type, period, time) DO NOTHING",
+        base_query,
+        placeholders.join(",")
+    );
+    client
+        .execute(
+            &query,
+            &params
+                .iter()
+                .map(|p| p.as_ref() as &(dyn tokio_postgres::types::ToSql + Sync))
+                .collect::<Vec<_>>(),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Points already stored are skipped.
+#[allow(dead_code)]
+pub async fn batch_insert_taker_volume(
+    client: &Client,
+    volumes: Vec<TakerVolumeEvent>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if volumes.is_empty() {
+        return Ok(());
+    }
+
+    let base_query = String::from(
+        "INSERT INTO binance.taker_volumes (
+            time, symbol, period, buy_sell_ratio, buy_volume, sell_volume, market
+        ) VALUES ",
+    );
+
+    let mut placeholders = Vec::new();
+    let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Sync + Send>> = Vec::new();
+
+    for (i, volume) in volumes.iter().enumerate() {
+        // Each record requires 7 parameters
+        let offset = i * 7;
+        placeholders.push(format!(
+            "(to_timestamp(${}::FLOAT8), ${}, ${}, ${}, ${}, ${}, ${})",
+            offset + 1,
+            offset + 2,
+            offset + 3,
+            offset + 4,
+            offset + 5,
+            offset + 6,
+            offset + 7,
+        ));
+
+        params.push(Box::new(volume.time as f64 / 1000.0));
+        params.push(Box::new(volume.symbol.clone()));
+        params.push(Box::new(volume.period.clone()));
+        params.push(Box::new(volume.buy_sell_ratio.parse::<f32>()?));
+        params.push(Box::new(volume.buy_volume.parse::<f32>()?));
+        params.push(Box::new(volume.sell_volume.parse::<f32>()?));
+        params.push(Box::new(volume.market.as_str()));
+    }
+
+    let query = format!(
+        "{}{} ON CONFLICT (market, symbol, period, time) DO NOTHING",
+        base_query,
+        placeholders.join(",")
+    );
+    client
+        .execute(
+            &query,
+            &params
+                .iter()
+                .map(|p| p.as_ref() as &(dyn tokio_postgres::types::ToSql + Sync))
+                .collect::<Vec<_>>(),
+        )
+        .await?;
+
+    Ok(())
+}
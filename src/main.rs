@@ -1,34 +1,302 @@
 mod cex;
 mod database;
 
-use cex::binance::websocket::{BinanceData, BinanceStreamBuilder};
-use clap::{Arg, Command};
-use database::postgres::{feature_writer, timescale_batch_writer};
-use log::{error, info};
+use cex::binance::{
+    agg_trade_backfill::{AggTradePager, AggTradeRange},
+    exchange_info::InstrumentRegistry,
+    market::BinanceMarket,
+    open_interest::FUTURES_DATA_PERIODS,
+    user_data::UserDataStreamBuilder,
+    websocket::{BinanceData, BinanceStreamBuilder},
+};
+use cex::bitget::{
+    inst_type::BitgetInstType,
+    order_book::DEPTH_CHANNELS as BITGET_DEPTH_CHANNELS,
+    websocket::{BitgetData, BitgetStreamBuilder},
+};
+use clap::{error::ErrorKind, Arg, ArgAction, ArgGroup, Command};
+use database::{
+    agg_trade::{batch_insert_agg_trade, last_agg_trade_ids},
+    postgres::{connect_to_timescaledb, feature_writer, timescale_batch_writer},
+};
+use log::{error, info, warn};
+use std::collections::{HashMap, HashSet};
 use std::io::{stdout, Write};
-use tokio::{signal, sync::mpsc, time::Instant};
+use tokio::{
+    signal,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
 
 struct Config {
-    symbol: String,
+    symbols: Vec<String>,
+    market: BinanceMarket,
+    open_interest_interval: Option<u64>,
+    sentiment_period: Option<String>,
+    auto_subscribe: Option<String>, // Quote suffix of new perpetuals to subscribe to
+    bitget: Option<(BitgetInstType, String)>, // instType and depth channel
+    backfill: Option<(u64, u64)>,   // Agg trade range in Unix milliseconds
 }
 
 fn parse_args() -> Config {
-    let matches = Command::new("Binance Data Collector")
+    let mut command = Command::new("Binance Data Collector")
         .version("1.0")
         .about("Collects data from Binance WebSocket")
         .arg(
-            Arg::new("symbol")
+            Arg::new("symbols")
                 .short('S')
-                .long("symbol")
-                .value_name("SYMBOL")
-                .help("The trading pair symbol (e.g., btcusdt)")
+                .long("symbols")
+                .visible_alias("symbol")
+                .value_name("SYMBOLS")
+                .help("Comma separated trading pair symbols (e.g., btcusdt,ethusdt)")
                 .value_parser(clap::value_parser!(String))
-                .required(true),
+                .value_delimiter(','),
+        )
+        .arg(
+            Arg::new("symbols-file")
+                .long("symbols-file")
+                .value_name("PATH")
+                .help("File with one symbol per line ('#' starts a comment)")
+                .value_parser(clap::value_parser!(String)),
+        )
+        .arg(
+            Arg::new("market")
+                .long("market")
+                .value_name("MARKET")
+                .help("Binance market the symbols trade on")
+                .value_parser(["usdm", "coinm", "spot"])
+                .default_value("usdm"),
+        )
+        .arg(
+            Arg::new("open-interest-interval")
+                .long("open-interest-interval")
+                .value_name("SECONDS")
+                .help("Poll open interest every SECONDS (disabled when omitted)")
+                .value_parser(clap::value_parser!(u64).range(1..)),
+        )
+        .arg(
+            Arg::new("sentiment-period")
+                .long("sentiment-period")
+                .value_name("PERIOD")
+                .help("Poll long/short ratios and taker volume of PERIOD (e.g., 5m)")
+                .value_parser(FUTURES_DATA_PERIODS),
+        )
+        .arg(
+            Arg::new("auto-subscribe")
+                .long("auto-subscribe")
+                .value_name("SUFFIX")
+                .help("Also collect perpetuals listed while running whose symbol ends with SUFFIX (e.g., USDT)")
+                .value_parser(clap::value_parser!(String)),
+        )
+        .arg(
+            Arg::new("bitget")
+                .long("bitget")
+                .help("Also collect Bitget depth, trades and tickers for the symbols")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("bitget-inst-type")
+                .long("bitget-inst-type")
+                .value_name("TYPE")
+                .help("Bitget product line the symbols trade on")
+                .value_parser(["SPOT", "USDT-FUTURES", "COIN-FUTURES", "USDC-FUTURES"])
+                .default_value("USDT-FUTURES")
+                .requires("bitget"),
+        )
+        .arg(
+            Arg::new("bitget-depth")
+                .long("bitget-depth")
+                .value_name("CHANNEL")
+                .help("Bitget depth channel: books (full book) or books1/books5/books15 (top levels)")
+                .value_parser(BITGET_DEPTH_CHANNELS)
+                .default_value("books")
+                .requires("bitget"),
+        )
+        .arg(
+            Arg::new("backfill-from")
+                .long("backfill-from")
+                .value_name("TIME")
+                .help("Backfill agg trades from TIME (YYYY-MM-DD or RFC 3339) and exit")
+                .value_parser(parse_time_arg),
+        )
+        .arg(
+            Arg::new("backfill-to")
+                .long("backfill-to")
+                .value_name("TIME")
+                .help("End of the agg trade backfill (defaults to now)")
+                .value_parser(parse_time_arg)
+                .requires("backfill-from"),
         )
-        .get_matches();
-    let symbol = matches.get_one::<String>("symbol").unwrap().to_string();
+        .group(
+            ArgGroup::new("symbol-source")
+                .args(["symbols", "symbols-file"])
+                .multiple(true)
+                .required(true),
+        );
+    let matches = command.get_matches_mut();
+
+    let mut symbols: Vec<String> = matches
+        .get_many::<String>("symbols")
+        .unwrap_or_default()
+        .cloned()
+        .collect();
+
+    if let Some(path) = matches.get_one::<String>("symbols-file") {
+        match read_symbols_file(path) {
+            Ok(from_file) => symbols.extend(from_file),
+            Err(e) => {
+                error!("Failed to read symbols file {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let mut seen = HashSet::new();
+    let symbols = symbols
+        .into_iter()
+        .map(|symbol| symbol.trim().to_lowercase())
+        .filter(|symbol| !symbol.is_empty() && seen.insert(symbol.clone()))
+        .collect::<Vec<_>>();
+
+    if symbols.is_empty() {
+        error!("No symbols given");
+        std::process::exit(1);
+    }
+
+    let market = matches
+        .get_one::<String>("market")
+        .and_then(|market| market.parse::<BinanceMarket>().ok())
+        .unwrap_or_default();
+
+    let open_interest_interval = matches.get_one::<u64>("open-interest-interval").copied();
 
-    Config { symbol }
+    let sentiment_period = matches.get_one::<String>("sentiment-period").cloned();
+
+    let auto_subscribe = matches.get_one::<String>("auto-subscribe").cloned();
+
+    let bitget = matches.get_flag("bitget").then(|| {
+        let inst_type = matches
+            .get_one::<String>("bitget-inst-type")
+            .and_then(|inst_type| inst_type.parse::<BitgetInstType>().ok())
+            .unwrap_or_default();
+        let depth = matches
+            .get_one::<String>("bitget-depth")
+            .cloned()
+            .unwrap_or_else(|| String::from("books"));
+        (inst_type, depth)
+    });
+
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+    let backfill = matches.get_one::<u64>("backfill-from").map(|start| {
+        let end = matches
+            .get_one::<u64>("backfill-to")
+            .copied()
+            .unwrap_or(now);
+        (*start, end)
+    });
+    if let Some((start, end)) = backfill {
+        if end > now {
+            command
+                .error(ErrorKind::ValueValidation, "--backfill-to is in the future")
+                .exit();
+        }
+        if start >= end {
+            command
+                .error(
+                    ErrorKind::ValueValidation,
+                    "--backfill-from must be before --backfill-to (or now)",
+                )
+                .exit();
+        }
+    }
+
+    Config {
+        symbols,
+        market,
+        open_interest_interval,
+        sentiment_period,
+        auto_subscribe,
+        bitget,
+        backfill,
+    }
+}
+
+/// Parses "2024-01-31" (midnight UTC) or an RFC 3339 timestamp into Unix milliseconds.
+fn parse_time_arg(value: &str) -> Result<u64, String> {
+    if let Ok(time) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Ok(time.timestamp_millis() as u64);
+    }
+    chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map(|date| {
+            date.and_time(chrono::NaiveTime::MIN)
+                .and_utc()
+                .timestamp_millis() as u64
+        })
+        .map_err(|_| format!("Invalid time {}, expected YYYY-MM-DD or RFC 3339", value))
+}
+
+// Agg trades are kept for 3 days, older gaps are left to an explicit backfill
+const MAX_RESUME_AGE: Duration = Duration::from_secs(3 * 24 * 60 * 60);
+
+/// Last stored agg trade ID of each of `symbols`, to backfill what was missed
+/// while the collector was down. Symbols whose last stored trade is older than
+/// `MAX_RESUME_AGE` start from the live stream.
+async fn load_last_agg_trade_ids(
+    symbols: &[String],
+    market: BinanceMarket,
+) -> Result<HashMap<String, u64>, Box<dyn std::error::Error>> {
+    let client = connect_to_timescaledb().await?;
+    let mut last_trades = last_agg_trade_ids(&client, market).await?;
+    let now = chrono::Utc::now().timestamp_millis() as u64;
+
+    let mut last_ids = HashMap::new();
+    for symbol in symbols {
+        let symbol = symbol.to_uppercase();
+        let Some((last_id, trade_time)) = last_trades.remove(&symbol) else {
+            continue;
+        };
+        if now.saturating_sub(trade_time) > MAX_RESUME_AGE.as_millis() as u64 {
+            warn!(
+                "Last stored {} agg trade is older than {} days, not resuming from it. Use --backfill-from and --backfill-to for older trades",
+                symbol,
+                MAX_RESUME_AGE.as_secs() / (24 * 60 * 60)
+            );
+            continue;
+        }
+        last_ids.insert(symbol, last_id);
+    }
+    Ok(last_ids)
+}
+
+/// Writes the agg trades of `symbols` between `start` and `end` straight to the
+/// database. Trades that are already stored are skipped.
+async fn backfill_agg_trades(
+    symbols: &[String],
+    market: BinanceMarket,
+    start: u64,
+    end: u64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let client = connect_to_timescaledb().await?;
+    for symbol in symbols {
+        let mut pager = AggTradePager::new(symbol, market, AggTradeRange::Time { start, end });
+        let mut total = 0;
+        while let Some(page) = pager.next_page().await? {
+            total += page.len();
+            batch_insert_agg_trade(&client, page).await?;
+        }
+        info!("Backfilled {} agg trades for {}", total, symbol);
+    }
+    Ok(())
+}
+
+fn read_symbols_file(path: &str) -> std::io::Result<Vec<String>> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(content
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or_default().trim())
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
 }
 
 fn format_elapsed_time(seconds: u64) -> String {
@@ -44,12 +312,64 @@ fn format_elapsed_time(seconds: u64) -> String {
 async fn main() {
     env_logger::init();
     let config = parse_args();
-    let symbol_data = config.symbol;
-    let symbol_feature = symbol_data.clone();
+    let symbols_data = config.symbols;
+    let market = config.market;
+    let open_interest_interval = config.open_interest_interval;
+    let sentiment_period = config.sentiment_period;
+    let auto_subscribe = config.auto_subscribe;
+    let bitget = config.bitget;
+    // Reject unknown or halted symbols before any stream is opened
+    let instruments = match InstrumentRegistry::load(market).await {
+        Ok(instruments) => instruments,
+        Err(e) => {
+            error!("Failed to load Binance {} exchange info: {}", market, e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = instruments.validate(&symbols_data) {
+        error!("{}", e);
+        std::process::exit(1);
+    }
+    for symbol in &symbols_data {
+        if let Some(instrument) = instruments.get(symbol) {
+            info!(
+                "{}: tick size {}, step size {}, contract type {}",
+                instrument.symbol,
+                instrument.tick_size,
+                instrument.step_size,
+                instrument.contract_type.as_deref().unwrap_or("-")
+            );
+        }
+    }
+
+    if let Some((start, end)) = config.backfill {
+        if let Err(e) = backfill_agg_trades(&symbols_data, market, start, end).await {
+            error!("Failed to backfill agg trades: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let last_agg_trade_ids = match load_last_agg_trade_ids(&symbols_data, market).await {
+        Ok(last_ids) => last_ids,
+        Err(e) => {
+            error!(
+                "Failed to load the last stored agg trades, trades missed while stopped won't be backfilled: {}",
+                e
+            );
+            HashMap::new()
+        }
+    };
+
+    // Strategy features are not keyed by symbol, so they follow the first symbol only
+    let symbol_feature = symbols_data[0].clone();
+    let symbol_feature_bitget = symbol_feature.clone();
 
     // Timescale DB writer
     let (tx_data, rx_data) = mpsc::channel::<BinanceData>(9999);
     let (tx_feature, rx_feature) = mpsc::channel::<BinanceData>(9999);
+    let (tx_bitget, rx_bitget) = mpsc::channel::<BitgetData>(9999);
+    let (tx_bitget_feature, rx_bitget_feature) = mpsc::channel::<BitgetData>(9999);
 
     // Monitor buffer usage
     tokio::spawn({
@@ -76,42 +396,110 @@ async fn main() {
 
     // Timescale DB writer
     tokio::spawn(async move {
-        if let Err(e) = timescale_batch_writer(rx_data).await {
+        if let Err(e) = timescale_batch_writer(rx_data, rx_bitget).await {
             error!("Failed to start timescale writer: {}", e);
         }
     });
 
     // Feature writer
     tokio::spawn(async move {
-        if let Err(e) = feature_writer(rx_feature).await {
+        if let Err(e) = feature_writer(rx_feature, rx_bitget_feature).await {
             error!("Failed to start feature writer: {}", e);
         }
     });
 
+    // Binance user data stream, only for accounts with an API key
+    if let Ok(api_key) = std::env::var("BINANCE_API_KEY") {
+        let tx_user_data = tx_data.clone();
+        tokio::spawn(async move {
+            if let Err(e) = UserDataStreamBuilder::new(&api_key)
+                .with_market(market)
+                .build(tx_user_data)
+                .await
+            {
+                error!("Failed to start Binance user data stream: {}", e);
+            }
+        });
+    }
+
+    // Bitget data stream
+    if let Some((inst_type, depth)) = bitget.clone() {
+        let bitget_symbols = symbols_data.clone();
+        tokio::spawn(async move {
+            if let Err(e) = BitgetStreamBuilder::with_symbols(&bitget_symbols)
+                .with_inst_type(inst_type)
+                .with_depth_channel(&depth)
+                .with_trades()
+                .with_ticker()
+                .build(tx_bitget)
+                .await
+            {
+                error!("Failed to connect to Bitget data stream: {}", e);
+            }
+        });
+    }
+
     // Binance data stream
     tokio::spawn(async move {
-        if let Err(e) = BinanceStreamBuilder::new(&symbol_data)
+        let mut builder = BinanceStreamBuilder::with_symbols(&symbols_data)
+            .with_market(market)
             // .with_depth()
             .with_agg_trade()
-            .build(tx_data)
-            .await
-        {
+            .with_last_agg_trade_ids(last_agg_trade_ids);
+        if let Some(seconds) = open_interest_interval {
+            builder = builder.with_open_interest(Duration::from_secs(seconds));
+        }
+        if let Some(period) = &sentiment_period {
+            builder = builder.with_sentiment(period);
+        }
+        // Settled contracts are unsubscribed instead of left streaming nothing
+        if market.is_futures() {
+            builder = match &auto_subscribe {
+                Some(suffix) => builder.with_auto_subscribe(suffix),
+                None => builder.with_contract_info(),
+            };
+        } else if auto_subscribe.is_some() {
+            error!(
+                "Auto subscribe needs contract info, which is not available on {}",
+                market
+            );
+        }
+
+        if let Err(e) = builder.build(tx_data).await {
             error!("Failed to connect to Binance Data stream: {}", e);
         }
     });
 
     // Binance feature stream
     tokio::spawn(async move {
-        if let Err(e) = BinanceStreamBuilder::new(&symbol_feature)
+        let mut builder = BinanceStreamBuilder::new(&symbol_feature)
+            .with_market(market)
             .with_depth()
-            .with_agg_trade()
-            .build(tx_feature)
-            .await
-        {
+            .with_agg_trade();
+        if market.is_futures() {
+            builder = builder.with_contract_info();
+        }
+
+        if let Err(e) = builder.build(tx_feature).await {
             error!("Failed to connect to Binance feature stream: {}", e);
         }
     });
 
+    // Bitget feature stream, its book joins the Binance book in the consolidated view
+    if let Some((inst_type, depth)) = bitget {
+        tokio::spawn(async move {
+            if let Err(e) = BitgetStreamBuilder::new(&symbol_feature_bitget)
+                .with_inst_type(inst_type)
+                .with_depth_channel(&depth)
+                .with_book_snapshots()
+                .build(tx_bitget_feature)
+                .await
+            {
+                error!("Failed to connect to Bitget feature stream: {}", e);
+            }
+        });
+    }
+
     tokio::select! {
         // Keep alive
         _ = signal::ctrl_c() => {